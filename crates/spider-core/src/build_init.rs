@@ -0,0 +1,198 @@
+//! The `init` task, which scaffolds a new build
+
+use crate::error::{ErrorKind, Result};
+use crate::invocation::script::{BUILD_SCRIPT, SETTINGS_SCRIPT};
+use crate::project::Project;
+use crate::task::options::TaskOption;
+use crate::task::{Task, TaskError, from_fn};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The name of the task that scaffolds a new build
+pub const INIT_TASK: &str = "init";
+/// The group of the `init` task
+pub const BUILD_SETUP_GROUP: &str = "build setup";
+
+/// The kinds of build the `init` task can generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitTemplate {
+    /// A single project without any plugins
+    #[default]
+    Empty,
+    /// A single project building a rust library
+    RustLibrary,
+    /// A root project with an application and a library subproject
+    MultiProject,
+}
+
+impl InitTemplate {
+    /// All templates, in the order they are listed to users
+    pub const ALL: [InitTemplate; 3] = [Self::Empty, Self::RustLibrary, Self::MultiProject];
+
+    /// Gets the name this template is selected with
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::RustLibrary => "rust-library",
+            Self::MultiProject => "multi-project",
+        }
+    }
+
+    /// Gets the names of all templates as a comma separated list
+    pub fn names() -> String {
+        Self::ALL.map(|t| t.name()).join(", ")
+    }
+
+    /// Finds a template by its name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// Gets the files of this template, relative to the root of the new build
+    pub fn files(&self, project_name: &str) -> Vec<(PathBuf, String)> {
+        let mut settings = "plugins! {\n    id \"settings\" version \"1.0.0\",\n}\n\n".to_string();
+        writeln!(settings, "rootProject.name = {project_name:?};").unwrap();
+        let library_build = "plugins! {\n    id \"rust-library\",\n}\n".to_string();
+        let library_source = "//! The library of this project\n".to_string();
+
+        let mut files = vec![];
+        match self {
+            Self::Empty => {
+                files.push((PathBuf::from(BUILD_SCRIPT), String::new()));
+            }
+            Self::RustLibrary => {
+                files.push((PathBuf::from(BUILD_SCRIPT), library_build));
+                files.push((PathBuf::from("src/lib.rs"), library_source));
+            }
+            Self::MultiProject => {
+                settings.push_str("\ninclude(\":app\", \":lib\");\n");
+                files.push((PathBuf::from(BUILD_SCRIPT), String::new()));
+                files.push((
+                    Path::new("app").join(BUILD_SCRIPT),
+                    "plugins! {\n    id \"rust-application\",\n}\n".to_string(),
+                ));
+                files.push((
+                    PathBuf::from("app/src/main.rs"),
+                    "fn main() {}\n".to_string(),
+                ));
+                files.push((Path::new("lib").join(BUILD_SCRIPT), library_build));
+                files.push((PathBuf::from("lib/src/lib.rs"), library_source));
+            }
+        }
+        files.insert(0, (PathBuf::from(SETTINGS_SCRIPT), settings));
+        files
+    }
+
+    /// Writes the files of this template into `dir`, without overwriting any existing file
+    pub fn generate(
+        &self,
+        dir: &Path,
+        project_name: &str,
+    ) -> std::result::Result<Vec<PathBuf>, ErrorKind> {
+        let files = self.files(project_name);
+        let existing = files
+            .iter()
+            .map(|(path, _)| dir.join(path))
+            .filter(|path| path.exists())
+            .collect::<Vec<_>>();
+        if !existing.is_empty() {
+            return Err(ErrorKind::WouldOverwrite { files: existing });
+        }
+
+        let mut generated = vec![];
+        for (path, text) in files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, text)?;
+            generated.push(path);
+        }
+        Ok(generated)
+    }
+}
+
+/// Registers the `init` task in the given project, generating builds into `dir`
+pub async fn register<P: AsRef<Path>>(project: &Project, dir: P) -> Result<Task> {
+    let dir = dir.as_ref().to_path_buf();
+    let task = project.tasks().await.register(INIT_TASK).await?;
+    task.set_type_name("InitBuild").await?;
+    task.set_group(BUILD_SETUP_GROUP).await?;
+    task.set_description("Initializes a new spider build.")
+        .await?;
+    task.add_option(TaskOption::with_value(
+        "type",
+        format!(
+            "The template of the build to create, one of: {}.",
+            InitTemplate::names()
+        ),
+    ))
+    .await?;
+    task.add_option(TaskOption::with_value(
+        "project-name",
+        "The name of the root project. Defaults to the name of the directory.",
+    ))
+    .await?;
+    task.do_last(from_fn(move |task: Task, _: Project| {
+        let dir = dir.clone();
+        async move {
+            let template = match option_value(&task, "type").await {
+                Some(name) => InitTemplate::from_name(&name).ok_or_else(|| {
+                    TaskError::fail(ErrorKind::InvalidTaskOption {
+                        path: INIT_TASK.to_string(),
+                        option: "type".to_string(),
+                        message: format!(
+                            "unknown template {name:?}, expected one of: {}",
+                            InitTemplate::names()
+                        ),
+                    })
+                })?,
+                None => InitTemplate::default(),
+            };
+            let project_name = option_value(&task, "project-name")
+                .await
+                .or_else(|| {
+                    dir.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                })
+                .unwrap_or_else(|| "root".to_string());
+            let generated = template
+                .generate(&dir, &project_name)
+                .map_err(TaskError::fail)?;
+            for path in generated {
+                println!("Created {}", path.display());
+            }
+            Ok(())
+        }
+    }))
+    .await?;
+    Ok(task)
+}
+
+/// Gets the value of a task option, if it was set
+async fn option_value(task: &Task, name: &str) -> Option<String> {
+    task.option(name)
+        .await
+        .and_then(|option| option.value().map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let generated = InitTemplate::MultiProject
+            .generate(dir.path(), "demo")
+            .unwrap();
+        assert!(generated.contains(&dir.path().join("lib/src/lib.rs")));
+        let settings = std::fs::read_to_string(dir.path().join(SETTINGS_SCRIPT)).unwrap();
+        assert!(settings.contains("rootProject.name = \"demo\";"));
+
+        let error = InitTemplate::Empty
+            .generate(dir.path(), "demo")
+            .unwrap_err();
+        assert!(matches!(error, ErrorKind::WouldOverwrite { .. }));
+    }
+}
@@ -0,0 +1,245 @@
+//! Dependencies of projects, and their substitution in composite builds.
+//!
+//! A project declares binary dependencies on modules by their `group:name:version` notation, or
+//! dependencies on other projects. When a build includes another build, the modules produced by
+//! the projects of the included build are substituted with those projects, so that consumers use
+//! their outputs instead of a published artifact.
+
+use crate::error::ErrorKind;
+use crate::lifecycle::{BuildPhase, PhaseTracker};
+use crate::shared::{Shared, shared};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Identifies a module by its group and name, such as `org.example:util`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleId {
+    group: String,
+    name: String,
+}
+
+impl ModuleId {
+    /// Creates a module id
+    pub fn new<G: AsRef<str>, N: AsRef<str>>(group: G, name: N) -> Self {
+        Self {
+            group: group.as_ref().to_string(),
+            name: name.as_ref().to_string(),
+        }
+    }
+
+    /// Gets the group of the module
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Gets the name of the module
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for ModuleId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.group, self.name)
+    }
+}
+
+/// A dependency of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dependency {
+    /// A binary dependency on a module, optionally of a specific version
+    Module {
+        id: ModuleId,
+        version: Option<String>,
+    },
+    /// A dependency on a project, by its path
+    Project { path: String },
+}
+
+impl Dependency {
+    /// Creates a module dependency from its `group:name` or `group:name:version` notation
+    pub fn module<S: AsRef<str>>(notation: S) -> Result<Self, ErrorKind> {
+        let notation = notation.as_ref();
+        let parts = notation.split(':').collect::<Vec<_>>();
+        let (group, name, version) = match parts.as_slice() {
+            [group, name] => (group, name, None),
+            [group, name, version] if !version.is_empty() => {
+                (group, name, Some(version.to_string()))
+            }
+            _ => {
+                return Err(ErrorKind::InvalidDependencyNotation {
+                    notation: notation.to_string(),
+                });
+            }
+        };
+        if group.is_empty() || name.is_empty() {
+            return Err(ErrorKind::InvalidDependencyNotation {
+                notation: notation.to_string(),
+            });
+        }
+        Ok(Self::Module {
+            id: ModuleId::new(group, name),
+            version,
+        })
+    }
+
+    /// Creates a dependency on the project at `path`
+    pub fn project<S: AsRef<str>>(path: S) -> Self {
+        Self::Project {
+            path: path.as_ref().to_string(),
+        }
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependency::Module { id, version: None } => write!(f, "{id}"),
+            Dependency::Module {
+                id,
+                version: Some(version),
+            } => write!(f, "{id}:{version}"),
+            Dependency::Project { path } => write!(f, "project {path:?}"),
+        }
+    }
+}
+
+/// The dependencies declared by a project, in declaration order
+#[derive(Debug, Clone)]
+pub struct DependencySet {
+    project_path: String,
+    phase: PhaseTracker,
+    dependencies: Shared<Vec<Dependency>>,
+}
+
+impl DependencySet {
+    /// Creates an empty set of dependencies of the project at `project_path`
+    pub(crate) fn new(project_path: &str, phase: PhaseTracker) -> Self {
+        Self {
+            project_path: project_path.to_string(),
+            phase,
+            dependencies: shared(vec![]),
+        }
+    }
+
+    /// Adds a dependency. Dependencies can't be added once the build is executing.
+    pub async fn add(&self, dependency: Dependency) -> Result<(), ErrorKind> {
+        self.phase.check(
+            &format!(
+                "add dependency {dependency} of project {:?}",
+                self.project_path
+            ),
+            BuildPhase::Configuration,
+        )?;
+        self.dependencies.write().await.push(dependency);
+        Ok(())
+    }
+
+    /// Gets every declared dependency
+    pub async fn all(&self) -> Vec<Dependency> {
+        self.dependencies.read().await.clone()
+    }
+}
+
+/// Replaces dependencies on modules with the projects that produce them
+#[derive(Debug, Clone, Default)]
+pub struct DependencySubstitutions {
+    substitutions: HashMap<ModuleId, String>,
+}
+
+impl DependencySubstitutions {
+    /// Creates substitutions that don't replace any module
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Substitutes every version of a module with the project at `path`
+    pub fn substitute<S: AsRef<str>>(&mut self, module: ModuleId, path: S) {
+        self.substitutions.insert(module, path.as_ref().to_string());
+    }
+
+    /// Gets the path of the project substituted for a module
+    pub fn project_for(&self, module: &ModuleId) -> Option<&str> {
+        self.substitutions.get(module).map(String::as_str)
+    }
+
+    /// Applies these substitutions to a dependency
+    pub fn apply(&self, dependency: &Dependency) -> Dependency {
+        match dependency {
+            Dependency::Module { id, .. } => match self.project_for(id) {
+                Some(path) => Dependency::project(path),
+                None => dependency.clone(),
+            },
+            Dependency::Project { .. } => dependency.clone(),
+        }
+    }
+}
+
+/// A repository modules are fetched from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Repository {
+    name: String,
+    url: String,
+}
+
+impl Repository {
+    /// Creates a repository
+    pub fn new<N: AsRef<str>, U: AsRef<str>>(name: N, url: U) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            url: url.as_ref().to_string(),
+        }
+    }
+
+    /// Gets the name of the repository
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the url of the repository
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// A dependency resolved for the tasks of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedDependency {
+    /// A module that no project of the build produces, fetched from a repository
+    Module {
+        id: ModuleId,
+        version: Option<String>,
+    },
+    /// A project of the build, consumed through the outputs of the task that builds its
+    /// artifact. Tasks consuming the dependencies of their project depend on that task.
+    Project {
+        path: String,
+        task: Option<String>,
+        files: Vec<PathBuf>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_module() {
+        let mut substitutions = DependencySubstitutions::new();
+        substitutions.substitute(ModuleId::new("org.example", "util"), ":library:util");
+
+        let dependency = Dependency::module("org.example:util:1.0").unwrap();
+        assert_eq!(dependency.to_string(), "org.example:util:1.0");
+        assert_eq!(
+            substitutions.apply(&dependency),
+            Dependency::project(":library:util")
+        );
+        let other = Dependency::module("org.example:other").unwrap();
+        assert_eq!(substitutions.apply(&other), other);
+        assert!(matches!(
+            Dependency::module("org.example"),
+            Err(ErrorKind::InvalidDependencyNotation { .. })
+        ));
+    }
+}
@@ -1,9 +1,13 @@
 //! Build errors
 
+use crate::lifecycle::BuildPhase;
 use crate::table::TableError;
+use crate::task::TaskError;
 use std::backtrace::Backtrace;
 use std::fmt::{Debug, Display, Formatter};
 use std::panic::Location;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
 use thiserror::Error;
 
 /// Some error occurred
@@ -12,6 +16,74 @@ pub enum ErrorKind {
     #[error(transparent)]
     TableError(#[from] TableError),
     #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("task {path:?} already exists")]
+    TaskAlreadyExists { path: String },
+    #[error("task {path:?} not found{}", did_you_mean(.suggestions))]
+    TaskNotFound {
+        path: String,
+        suggestions: Vec<String>,
+    },
+    #[error("task {path:?} is ambiguous, candidates are: {}", .candidates.join(", "))]
+    AmbiguousTask {
+        path: String,
+        candidates: Vec<String>,
+    },
+    #[error("project {path:?} not found{}", did_you_mean(.suggestions))]
+    ProjectNotFound {
+        path: String,
+        suggestions: Vec<String>,
+    },
+    #[error("project {path:?} is ambiguous, candidates are: {}", .candidates.join(", "))]
+    AmbiguousProject {
+        path: String,
+        candidates: Vec<String>,
+    },
+    #[error("circular dependency between tasks: {}", .cycle.join(" -> "))]
+    DependencyCycle { cycle: Vec<String> },
+    #[error("unknown command line option '--{option}'")]
+    UnknownOption { option: String },
+    #[error("unknown command line option '--{option}' for task {path:?}")]
+    UnknownTaskOption { path: String, option: String },
+    #[error("invalid command line option '--{option}' for task {path:?}: {message}")]
+    InvalidTaskOption {
+        path: String,
+        option: String,
+        message: String,
+    },
+    #[error("execution failed for task {path:?}: {source}")]
+    TaskFailed {
+        path: String,
+        source: Box<TaskError>,
+    },
+    #[error("{} tasks failed:{}", .failures.len(), list_failures(.failures))]
+    MultipleFailures { failures: Vec<Error> },
+    #[error("refusing to overwrite existing files: {}", list_paths(.files))]
+    WouldOverwrite { files: Vec<PathBuf> },
+    #[error("could not compile {}: {source}", .path.display())]
+    ScriptCompilation {
+        path: PathBuf,
+        source: spider_rs_compiler::CompileError,
+    },
+    #[error("{}:{line}: {message}", .path.display())]
+    ScriptEvaluation {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error("invalid project path {path:?}")]
+    InvalidProjectPath { path: String },
+    #[error("included build {} does not exist", .dir.display())]
+    IncludedBuildNotFound { dir: PathBuf },
+    #[error("included build {name:?} conflicts with a project or build of the same name")]
+    BuildNameConflict { name: String },
+    #[error(
+        "invalid dependency notation {notation:?}, expected 'group:name' or 'group:name:version'"
+    )]
+    InvalidDependencyNotation { notation: String },
+    #[error("cannot {action} during the {phase} phase")]
+    IllegalPhase { action: String, phase: BuildPhase },
+    #[error(transparent)]
     Custom { error: CustomError },
 }
 
@@ -23,6 +95,32 @@ impl ErrorKind {
     }
 }
 
+/// Formats suggestions for a name that could not be found
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(", did you mean: {}?", suggestions.join(", "))
+    }
+}
+
+/// Formats each failure on its own line
+fn list_failures(failures: &[Error]) -> String {
+    failures
+        .iter()
+        .map(|failure| format!("\n  - {}", failure.kind))
+        .collect()
+}
+
+/// Formats paths as a comma separated list
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub struct CustomError(pub Box<dyn ToString + Send + Sync>);
 
 impl Display for CustomError {
@@ -57,14 +155,52 @@ impl Debug for Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        writeln!(
-            f,
-            "error in thread {thread} at {location}: {error}",
-            thread = self.thread_name,
-            location = self.location,
-            error = self.kind
-        )?;
-        write!(f, "{}", self.trace)
+        write!(f, "{}\n  at {}", self.kind, self.location)?;
+        match show_stacktrace() {
+            ShowStacktrace::Never => Ok(()),
+            ShowStacktrace::Always => {
+                write!(
+                    f,
+                    "\nstack trace of thread {}:\n{}",
+                    self.thread_name, self.trace
+                )
+            }
+            ShowStacktrace::Full => {
+                write!(
+                    f,
+                    "\nstack trace of thread {}:\n{:#}",
+                    self.thread_name, self.trace
+                )
+            }
+        }
+    }
+}
+
+/// How much of the stack trace of an error is shown
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ShowStacktrace {
+    /// Only the message and location of an error
+    #[default]
+    Never,
+    /// The stack trace, without the frames of the runtime
+    Always,
+    /// Every frame of the stack trace
+    Full,
+}
+
+static SHOW_STACKTRACE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how errors are displayed. Stack traces are only captured when they will be shown.
+pub fn set_show_stacktrace(show_stacktrace: ShowStacktrace) {
+    SHOW_STACKTRACE.store(show_stacktrace as u8, Ordering::Relaxed);
+}
+
+/// Gets how errors are displayed
+pub fn show_stacktrace() -> ShowStacktrace {
+    match SHOW_STACKTRACE.load(Ordering::Relaxed) {
+        0 => ShowStacktrace::Never,
+        1 => ShowStacktrace::Always,
+        _ => ShowStacktrace::Full,
     }
 }
 
@@ -78,7 +214,10 @@ macro_rules! create_error {
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| format!("{:?}", thread.id())),
             location: Location::caller().clone(),
-            trace: Backtrace::capture(),
+            trace: match show_stacktrace() {
+                ShowStacktrace::Never => Backtrace::disabled(),
+                _ => Backtrace::force_capture(),
+            },
         }
     }};
 }
@@ -111,4 +250,13 @@ mod tests {
         let error = Error::from(ErrorKind::custom("error!"));
         println!("{}", error);
     }
+
+    #[test]
+    fn test_display_without_stacktrace() {
+        let error = Error::from(ErrorKind::custom("error!"));
+        assert_eq!(
+            error.to_string(),
+            format!("error!\n  at {}", error.location)
+        );
+    }
 }
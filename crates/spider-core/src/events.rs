@@ -0,0 +1,133 @@
+//! Events emitted over the lifetime of a build, for consoles and other observers
+
+use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How the execution of a task ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    /// Every action of the task completed
+    Success,
+    /// An action of the task failed
+    Failed { message: String },
+    /// The task was not started because one of its dependencies did not complete
+    NotExecuted { dependency: String },
+    /// The task was not started because the build is a dry run
+    Skipped,
+}
+
+/// Something that happened during a build
+#[derive(Debug, Clone)]
+pub enum BuildEvent {
+    /// The settings of the build were evaluated
+    SettingsEvaluated {
+        root_dir: PathBuf,
+        settings_script: Option<PathBuf>,
+    },
+    /// A project was configured
+    ProjectConfigured { path: String },
+    /// The tasks to execute were determined, in execution order
+    TaskGraphReady { tasks: Vec<String> },
+    /// A task started executing
+    TaskStarted { path: String },
+    /// A task finished. Tasks that were not executed finish without being started.
+    TaskFinished {
+        path: String,
+        outcome: TaskOutcome,
+        duration: Duration,
+    },
+    /// The build finished, with the message of its error if it failed
+    BuildFinished { failure: Option<String> },
+}
+
+impl Display for TaskOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskOutcome::Success => write!(f, "succeeded"),
+            TaskOutcome::Failed { message } => write!(f, "failed: {message}"),
+            TaskOutcome::NotExecuted { dependency } => {
+                write!(f, "not executed, {dependency} did not complete")
+            }
+            TaskOutcome::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+impl Display for BuildEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildEvent::SettingsEvaluated { root_dir, .. } => {
+                write!(f, "settings evaluated in {}", root_dir.display())
+            }
+            BuildEvent::ProjectConfigured { path } => write!(f, "project {path} configured"),
+            BuildEvent::TaskGraphReady { tasks } => {
+                write!(f, "task graph ready: {}", tasks.join(", "))
+            }
+            BuildEvent::TaskStarted { path } => write!(f, "task {path} started"),
+            BuildEvent::TaskFinished {
+                path,
+                outcome,
+                duration,
+            } => write!(f, "task {path} {outcome} in {duration:?}"),
+            BuildEvent::BuildFinished { failure: None } => write!(f, "build succeeded"),
+            BuildEvent::BuildFinished {
+                failure: Some(failure),
+            } => write!(f, "build failed: {failure}"),
+        }
+    }
+}
+
+/// Receives the events of a build
+pub trait BuildListener: Send + Sync {
+    /// Called for every event, in the order the events happen
+    fn on_event(&self, event: &BuildEvent);
+}
+
+impl<F: Fn(&BuildEvent) + Send + Sync> BuildListener for F {
+    fn on_event(&self, event: &BuildEvent) {
+        self(event)
+    }
+}
+
+/// The listeners of a build
+#[derive(Clone, Default)]
+pub struct BuildListeners {
+    listeners: Vec<Arc<dyn BuildListener>>,
+}
+
+impl BuildListeners {
+    /// Creates an empty set of listeners
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a listener
+    pub fn add<L: BuildListener + 'static>(&mut self, listener: L) {
+        self.listeners.push(Arc::new(listener));
+    }
+
+    /// Sends an event to every listener
+    pub fn emit(&self, event: BuildEvent) {
+        for listener in &self.listeners {
+            listener.on_event(&event);
+        }
+    }
+}
+
+impl BuildListener for BuildListeners {
+    fn on_event(&self, event: &BuildEvent) {
+        for listener in &self.listeners {
+            listener.on_event(event);
+        }
+    }
+}
+
+impl Debug for BuildListeners {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuildListeners")
+            .field("len", &self.listeners.len())
+            .finish()
+    }
+}
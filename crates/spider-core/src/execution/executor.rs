@@ -0,0 +1,198 @@
+//! The [`TaskExecutor`], which runs the tasks of a [`TaskGraph`]
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::events::{BuildEvent, BuildListeners, TaskOutcome};
+use crate::execution::graph::TaskGraph;
+use crate::project::Project;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Executes the tasks of a task graph in order
+#[derive(Debug, Default)]
+pub struct TaskExecutor {
+    continue_on_failure: bool,
+    listeners: BuildListeners,
+}
+
+impl TaskExecutor {
+    /// Creates a new executor that stops at the first failure
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether tasks that don't depend on a failed task are still executed
+    pub fn set_continue_on_failure(&mut self, continue_on_failure: bool) {
+        self.continue_on_failure = continue_on_failure;
+    }
+
+    /// Sets the listeners notified when tasks start and finish
+    pub fn set_listeners(&mut self, listeners: BuildListeners) {
+        self.listeners = listeners;
+    }
+
+    /// Executes every task in the graph, each with the project it belongs to among the trees of
+    /// `root_projects`.
+    ///
+    /// When continuing on failure, tasks that depend on a failed task are not executed, and all
+    /// failures are reported together once no more tasks can run.
+    pub async fn execute(&self, graph: &TaskGraph, root_projects: &[Project]) -> Result<()> {
+        let mut failures: Vec<Error> = vec![];
+        let mut blocked: HashSet<&str> = HashSet::new();
+        for (path, task) in graph.tasks() {
+            if let Some(dependency) = graph
+                .dependencies_of(path)
+                .iter()
+                .find(|dependency| blocked.contains(dependency.as_str()))
+            {
+                self.listeners.emit(BuildEvent::TaskFinished {
+                    path: path.to_string(),
+                    outcome: TaskOutcome::NotExecuted {
+                        dependency: dependency.clone(),
+                    },
+                    duration: Duration::ZERO,
+                });
+                blocked.insert(path);
+                continue;
+            }
+
+            let project_path = task.project_path().await;
+            let Some(project) = root_projects
+                .iter()
+                .find_map(|root| root.find_project(&project_path))
+            else {
+                return Err(ErrorKind::ProjectNotFound {
+                    path: project_path,
+                    suggestions: vec![],
+                }
+                .into());
+            };
+            self.listeners.emit(BuildEvent::TaskStarted {
+                path: path.to_string(),
+            });
+            let start = Instant::now();
+            let result = task.execute(project).await;
+            let outcome = match &result {
+                Ok(()) => TaskOutcome::Success,
+                Err(source) => TaskOutcome::Failed {
+                    message: source.to_string(),
+                },
+            };
+            self.listeners.emit(BuildEvent::TaskFinished {
+                path: path.to_string(),
+                outcome,
+                duration: start.elapsed(),
+            });
+            if let Err(source) = result {
+                let error = Error::from(ErrorKind::TaskFailed {
+                    path: path.to_string(),
+                    source: Box::new(source),
+                });
+                if !self.continue_on_failure {
+                    return Err(error);
+                }
+                tracing::error!("{}", error.kind);
+                failures.push(error);
+                blocked.insert(path);
+            }
+        }
+
+        match failures.len() {
+            0 => Ok(()),
+            1 => Err(failures.pop().unwrap()),
+            _ => Err(ErrorKind::MultipleFailures { failures }.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Task, TaskError, from_fn};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_continue_after_failure() {
+        let executed = Arc::new(AtomicUsize::new(0));
+        let mut available = HashMap::new();
+        let mut requested = vec![];
+        for (path, fails, dependency) in [
+            (":a", true, None),
+            (":b", true, None),
+            (":c", false, Some(":a")),
+            (":d", false, None),
+        ] {
+            let task = Task::new(path);
+            if let Some(dependency) = dependency {
+                task.depends_on(dependency).await.unwrap();
+            }
+            let executed = executed.clone();
+            task.do_last(from_fn(move |_, _| {
+                executed.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if fails {
+                        Err(TaskError::fail(ErrorKind::custom("failed")))
+                    } else {
+                        Ok(())
+                    }
+                }
+            }))
+            .await
+            .unwrap();
+            requested.push(task.clone());
+            available.insert(path.to_string(), task);
+        }
+        let graph = TaskGraph::new(&requested, &available, &HashSet::new())
+            .await
+            .unwrap();
+
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut listeners = BuildListeners::new();
+        listeners.add({
+            let events = events.clone();
+            move |event: &BuildEvent| {
+                if let BuildEvent::TaskFinished { path, outcome, .. } = event {
+                    events.lock().unwrap().push((path.clone(), outcome.clone()));
+                }
+            }
+        });
+        let mut executor = TaskExecutor::new();
+        executor.set_continue_on_failure(true);
+        executor.set_listeners(listeners);
+        let error = executor
+            .execute(&graph, &[Project::new()])
+            .await
+            .unwrap_err();
+        let ErrorKind::MultipleFailures { failures } = error.kind else {
+            panic!("expected multiple failures")
+        };
+        assert_eq!(failures.len(), 2);
+        // :c depends on the failed :a, so only :a, :b and :d run
+        assert_eq!(executed.load(Ordering::SeqCst), 3);
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[2],
+            (
+                ":c".to_string(),
+                TaskOutcome::NotExecuted {
+                    dependency: ":a".to_string()
+                }
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_without_project() {
+        let task = Task::new(":missing:a");
+        let available = HashMap::from([(":missing:a".to_string(), task.clone())]);
+        let graph = TaskGraph::new(&[task], &available, &HashSet::new())
+            .await
+            .unwrap();
+        let error = TaskExecutor::new()
+            .execute(&graph, &[Project::new()])
+            .await
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::ProjectNotFound { .. }));
+    }
+}
@@ -0,0 +1,287 @@
+//! The [`TaskGraph`], the ordered set of tasks a build will execute
+
+use crate::error::{ErrorKind, Result};
+use crate::fs::watch::FileWatchSet;
+use crate::task::Task;
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The tasks to execute for a build, and the dependencies between them
+#[derive(Debug, Clone)]
+pub struct TaskGraph {
+    /// Tasks in execution order
+    tasks: IndexMap<String, Task>,
+    dependencies: HashMap<String, Vec<String>>,
+}
+
+impl TaskGraph {
+    /// Computes the graph of the requested tasks and everything they depend on.
+    ///
+    /// Dependencies are executed before the tasks that need them, and otherwise tasks run in
+    /// the order they were requested. Excluded tasks are left out of the graph along with any
+    /// dependencies that are only needed by them.
+    pub async fn new(
+        requested: &[Task],
+        available: &HashMap<String, Task>,
+        excluded: &HashSet<String>,
+    ) -> Result<Self> {
+        Self::with_inferred_dependencies(requested, available, excluded, &HashMap::new()).await
+    }
+
+    /// Computes the graph like [`new`](Self::new), with the `inferred` dependencies of tasks, by
+    /// their path, added to those the tasks declare
+    pub async fn with_inferred_dependencies(
+        requested: &[Task],
+        available: &HashMap<String, Task>,
+        excluded: &HashSet<String>,
+        inferred: &HashMap<String, Vec<String>>,
+    ) -> Result<Self> {
+        let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+        let mut roots = vec![];
+        let mut queue = vec![];
+        for task in requested {
+            let path = task.path().await;
+            if !excluded.contains(&path) {
+                roots.push(path.clone());
+                queue.push(path);
+            }
+        }
+        while let Some(path) = queue.pop() {
+            if dependencies.contains_key(&path) {
+                continue;
+            }
+            let task = available
+                .get(&path)
+                .ok_or_else(|| ErrorKind::TaskNotFound {
+                    path: path.clone(),
+                    suggestions: vec![],
+                })?;
+            let mut task_dependencies = task.dependencies().await;
+            for dependency in inferred.get(&path).into_iter().flatten() {
+                if !task_dependencies.contains(dependency) {
+                    task_dependencies.push(dependency.clone());
+                }
+            }
+            task_dependencies.retain(|dependency| !excluded.contains(dependency));
+            queue.extend(task_dependencies.iter().cloned());
+            dependencies.insert(path, task_dependencies);
+        }
+
+        let mut order = Ordering {
+            dependencies: &dependencies,
+            visiting: vec![],
+            visited: HashSet::new(),
+            ordered: vec![],
+        };
+        for root in &roots {
+            order
+                .visit(root)
+                .map_err(|cycle| ErrorKind::DependencyCycle { cycle })?;
+        }
+        let tasks = order
+            .ordered
+            .into_iter()
+            .map(|path| {
+                let task = available[&path].clone();
+                (path, task)
+            })
+            .collect();
+        Ok(Self {
+            tasks,
+            dependencies,
+        })
+    }
+
+    /// Gets the tasks of this graph in execution order
+    pub fn tasks(&self) -> impl Iterator<Item = (&str, &Task)> {
+        self.tasks.iter().map(|(path, task)| (path.as_str(), task))
+    }
+
+    /// Gets the paths of the tasks in this graph in execution order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.tasks.keys().map(String::as_str)
+    }
+
+    /// Checks if this graph contains a task
+    pub fn contains(&self, path: &str) -> bool {
+        self.tasks.contains_key(path)
+    }
+
+    /// Gets the direct dependencies of a task in this graph
+    pub fn dependencies_of(&self, path: &str) -> &[String] {
+        self.dependencies
+            .get(path)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Gets the number of tasks in this graph
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Checks if this graph has no tasks
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Gets the declared inputs of every task in this graph. Relative inputs are resolved
+    /// against `base_dir`.
+    pub async fn inputs(&self, base_dir: &Path) -> FileWatchSet {
+        let mut inputs = FileWatchSet::new();
+        for task in self.tasks.values() {
+            inputs.extend(task.inputs().await.iter().map(|input| base_dir.join(input)));
+        }
+        inputs
+    }
+
+    /// Gets the tasks affected by changes to the given files, in execution order.
+    ///
+    /// A task is affected when one of its inputs changed, or when it depends on an affected task.
+    pub async fn affected_by(&self, base_dir: &Path, changed: &[PathBuf]) -> Vec<String> {
+        let mut affected: Vec<String> = vec![];
+        for (path, task) in &self.tasks {
+            let inputs = task
+                .inputs()
+                .await
+                .iter()
+                .map(|input| base_dir.join(input))
+                .collect::<FileWatchSet>();
+            let depends_on_affected = self
+                .dependencies_of(path)
+                .iter()
+                .any(|dependency| affected.contains(dependency));
+            if depends_on_affected || changed.iter().any(|change| inputs.matches(change)) {
+                affected.push(path.clone());
+            }
+        }
+        affected
+    }
+}
+
+/// Depth-first topological ordering of task paths
+struct Ordering<'a> {
+    dependencies: &'a HashMap<String, Vec<String>>,
+    visiting: Vec<String>,
+    visited: HashSet<String>,
+    ordered: Vec<String>,
+}
+
+impl Ordering<'_> {
+    /// Visits a task after its dependencies, returning the cycle if one is found
+    fn visit(&mut self, path: &str) -> std::result::Result<(), Vec<String>> {
+        if self.visited.contains(path) {
+            return Ok(());
+        }
+        if let Some(start) = self.visiting.iter().position(|p| p == path) {
+            let mut cycle = self.visiting[start..].to_vec();
+            cycle.push(path.to_string());
+            return Err(cycle);
+        }
+        self.visiting.push(path.to_string());
+        for dependency in &self.dependencies[path] {
+            self.visit(dependency)?;
+        }
+        self.visiting.pop();
+        self.visited.insert(path.to_string());
+        self.ordered.push(path.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn tasks(graph: &[(&str, &[&str])]) -> HashMap<String, Task> {
+        let mut tasks = HashMap::new();
+        for (path, dependencies) in graph {
+            let task = Task::new(path);
+            for dependency in *dependencies {
+                task.depends_on(dependency).await.unwrap();
+            }
+            tasks.insert(path.to_string(), task);
+        }
+        tasks
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_run_first() {
+        let available = tasks(&[
+            (":build", &[":compile", ":test"]),
+            (":test", &[":compile"]),
+            (":compile", &[]),
+            (":clean", &[]),
+        ])
+        .await;
+        let requested = [available[":clean"].clone(), available[":build"].clone()];
+        let graph = TaskGraph::new(&requested, &available, &HashSet::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            graph.paths().collect::<Vec<_>>(),
+            [":clean", ":compile", ":test", ":build"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exclusion() {
+        let available = tasks(&[
+            (":build", &[":compile", ":test"]),
+            (":test", &[":compile", ":testClasses"]),
+            (":testClasses", &[]),
+            (":compile", &[]),
+        ])
+        .await;
+        let excluded = HashSet::from([":test".to_string()]);
+        let graph = TaskGraph::new(&[available[":build"].clone()], &available, &excluded)
+            .await
+            .unwrap();
+        assert_eq!(graph.paths().collect::<Vec<_>>(), [":compile", ":build"]);
+    }
+
+    #[tokio::test]
+    async fn test_cycle() {
+        let available = tasks(&[(":a", &[":b"]), (":b", &[":a"])]).await;
+        let error = TaskGraph::new(&[available[":a"].clone()], &available, &HashSet::new())
+            .await
+            .unwrap_err();
+        let ErrorKind::DependencyCycle { cycle } = error.kind else {
+            panic!("expected a cycle")
+        };
+        assert_eq!(cycle, [":a", ":b", ":a"]);
+    }
+
+    #[tokio::test]
+    async fn test_affected_by() {
+        let available = tasks(&[
+            (":build", &[":compile", ":test"]),
+            (":test", &[":compile"]),
+            (":compile", &[]),
+            (":docs", &[]),
+        ])
+        .await;
+        available[":compile"].add_input("src").await.unwrap();
+        available[":docs"].add_input("README.md").await.unwrap();
+        let requested = [available[":build"].clone(), available[":docs"].clone()];
+        let graph = TaskGraph::new(&requested, &available, &HashSet::new())
+            .await
+            .unwrap();
+
+        let root = Path::new("/project");
+        assert!(graph.inputs(root).await.matches("/project/src/lib.rs"));
+        assert_eq!(
+            graph
+                .affected_by(root, &[PathBuf::from("/project/src/lib.rs")])
+                .await,
+            [":compile", ":test", ":build"]
+        );
+        assert_eq!(
+            graph
+                .affected_by(root, &[PathBuf::from("/project/README.md")])
+                .await,
+            [":docs"]
+        );
+    }
+}
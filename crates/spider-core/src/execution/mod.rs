@@ -0,0 +1,4 @@
+//! Planning and executing the tasks of a build
+
+pub mod executor;
+pub mod graph;
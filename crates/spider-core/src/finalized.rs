@@ -21,6 +21,11 @@ impl<T> Finalize<T> {
     pub fn finalize(&mut self) {
         self.finalized = true;
     }
+
+    /// Allows mutable access to this object again
+    pub(crate) fn unfinalize(&mut self) {
+        self.finalized = false;
+    }
 }
 
 impl<T> Deref for Finalize<T> {
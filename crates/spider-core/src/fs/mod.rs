@@ -2,3 +2,18 @@
 
 pub mod file;
 pub mod layout;
+pub mod watch;
+
+use std::path::PathBuf;
+
+/// The environment variable overriding the spider user home
+pub const USER_HOME_ENV: &str = "SPIDER_USER_HOME";
+
+/// Gets the spider user home, `$SPIDER_USER_HOME` or `~/.spider`, which holds state shared by
+/// all builds of a user
+pub fn user_home_dir() -> Option<PathBuf> {
+    match std::env::var_os(USER_HOME_ENV) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".spider")),
+    }
+}
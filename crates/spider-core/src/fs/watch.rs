@@ -0,0 +1,57 @@
+//! Sets of files and directories to watch for changes
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Files and directories whose changes are relevant to a build.
+///
+/// A watched directory covers everything inside it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileWatchSet {
+    paths: BTreeSet<PathBuf>,
+}
+
+impl FileWatchSet {
+    /// Creates an empty watch set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watches a file or directory
+    pub fn add<P: AsRef<Path>>(&mut self, path: P) {
+        self.paths.insert(path.as_ref().to_path_buf());
+    }
+
+    /// Gets the watched paths
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+
+    /// Checks if nothing is watched
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Checks if a change to `path` is relevant, because it is watched or inside a watched
+    /// directory
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        self.paths.iter().any(|watched| path.starts_with(watched))
+    }
+}
+
+impl<P: AsRef<Path>> Extend<P> for FileWatchSet {
+    fn extend<T: IntoIterator<Item = P>>(&mut self, iter: T) {
+        for path in iter {
+            self.add(path);
+        }
+    }
+}
+
+impl<P: AsRef<Path>> FromIterator<P> for FileWatchSet {
+    fn from_iter<T: IntoIterator<Item = P>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
@@ -0,0 +1,250 @@
+//! Init scripts, which apply conventions to every build run by a user or machine.
+//!
+//! Init scripts given with `--init-script` are evaluated first, followed by the scripts in the
+//! `init.d` directory of the spider user home in file name order. They are evaluated before the
+//! settings script, and declare [`InitConventions`]:
+//!
+//! ```text
+//! repositories.add("company", "https://repo.example.com/maven");
+//! allprojects.apply("com.example.conventions");
+//! listeners.log("/var/log/spider/builds.log");
+//! ```
+//!
+//! These three statements are the subset of the build that init scripts can reach. Hooks of the
+//! build lifecycle and other listeners are added in code, with
+//! [`Spider::hooks`](crate::invocation::spider::Spider::hooks) and
+//! [`Spider::add_listener`](crate::invocation::spider::Spider::add_listener).
+
+use crate::dependencies::Repository;
+use crate::error::ErrorKind;
+use crate::events::{BuildEvent, BuildListener, BuildListeners};
+use crate::invocation::script::ScriptSource;
+use crate::project::Project;
+use indexmap::IndexSet;
+use spider_rs_compiler::{Expr, StatementKind};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The directory of the spider user home init scripts are read from
+pub const INIT_SCRIPTS_DIR: &str = "init.d";
+/// The suffix of the file names of init scripts
+pub const INIT_SCRIPT_SUFFIX: &str = ".spider.rs";
+
+/// Gets the init scripts in the `init.d` directory of the spider user home, in file name order
+pub fn user_init_scripts(user_home: &Path) -> io::Result<Vec<PathBuf>> {
+    let dir = user_home.join(INIT_SCRIPTS_DIR);
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut scripts = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_script = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(INIT_SCRIPT_SUFFIX));
+        if is_script && path.is_file() {
+            scripts.push(path);
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// The conventions init scripts apply to a build: repositories and plugins added to every
+/// project, and listeners notified of the events of the build
+#[derive(Debug, Clone, Default)]
+pub struct InitConventions {
+    repositories: Vec<Repository>,
+    plugins: IndexSet<String>,
+    listeners: BuildListeners,
+}
+
+impl InitConventions {
+    /// Creates conventions that don't change the build
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a repository to every project
+    pub fn add_repository(&mut self, repository: Repository) {
+        self.repositories.push(repository);
+    }
+
+    /// Gets the repositories added to every project
+    pub fn repositories(&self) -> &[Repository] {
+        &self.repositories
+    }
+
+    /// Applies a plugin to every project
+    pub fn apply_plugin<S: AsRef<str>>(&mut self, id: S) {
+        self.plugins.insert(id.as_ref().to_string());
+    }
+
+    /// Gets the ids of the plugins applied to every project
+    pub fn plugins(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(String::as_str)
+    }
+
+    /// Adds a listener notified of the events of the build
+    pub fn add_listener<L: BuildListener + 'static>(&mut self, listener: L) {
+        self.listeners.add(listener);
+    }
+
+    /// Gets the listeners added by these conventions
+    pub fn listeners(&self) -> &BuildListeners {
+        &self.listeners
+    }
+
+    /// Applies these conventions to a project
+    pub fn apply_to(&self, project: &Project) -> Result<(), ErrorKind> {
+        for repository in &self.repositories {
+            project.add_repository(repository.clone())?;
+        }
+        for plugin in &self.plugins {
+            project.apply_plugin(plugin)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates an init script, adding the conventions it declares
+    pub fn evaluate(&mut self, script: &ScriptSource) -> Result<(), ErrorKind> {
+        let base_dir = script.path().parent().unwrap_or(Path::new("."));
+        for statement in script.compile()?.statements() {
+            self.evaluate_statement(&statement.kind, base_dir)
+                .map_err(|message| ErrorKind::ScriptEvaluation {
+                    path: script.path().to_path_buf(),
+                    line: statement.line,
+                    message,
+                })?;
+        }
+        Ok(())
+    }
+
+    fn evaluate_statement(
+        &mut self,
+        statement: &StatementKind,
+        base_dir: &Path,
+    ) -> Result<(), String> {
+        let StatementKind::Expr(Expr::MethodCall {
+            receiver,
+            method,
+            args,
+        }) = statement
+        else {
+            return Err(unsupported(statement));
+        };
+        let Expr::Ident(receiver) = &**receiver else {
+            return Err(unsupported(statement));
+        };
+        let args = args
+            .iter()
+            .map(|arg| {
+                arg.as_str()
+                    .ok_or_else(|| format!("expected a string, found `{arg}`"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match (receiver.as_str(), method.as_str(), args.as_slice()) {
+            ("repositories", "add", [name, url]) => {
+                self.add_repository(Repository::new(name, url));
+                Ok(())
+            }
+            ("allprojects", "apply", [id]) => {
+                self.apply_plugin(id);
+                Ok(())
+            }
+            ("listeners", "log", [path]) => {
+                let log = EventLog::open(&base_dir.join(path))
+                    .map_err(|e| format!("could not open {path}: {e}"))?;
+                self.add_listener(log);
+                Ok(())
+            }
+            _ => Err(unsupported(statement)),
+        }
+    }
+}
+
+fn unsupported(statement: &StatementKind) -> String {
+    match statement {
+        StatementKind::Expr(expr) => format!("`{expr}` is not supported in an init script"),
+        StatementKind::Assign { target, .. } => {
+            format!("`{target}` can't be assigned in an init script")
+        }
+        StatementKind::Plugins(_) => "plugins! is not supported in an init script".to_string(),
+    }
+}
+
+/// Appends a line describing each event of a build to a file
+#[derive(Debug)]
+struct EventLog {
+    file: Mutex<File>,
+}
+
+impl EventLog {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl BuildListener for EventLog {
+    fn on_event(&self, event: &BuildEvent) {
+        if let Err(error) = writeln!(self.file.lock().unwrap(), "{event}") {
+            tracing::warn!("could not log build event: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_init_script() {
+        let home = tempfile::tempdir().unwrap();
+        let dir = home.path().join(INIT_SCRIPTS_DIR);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(
+            dir.join("b-conventions.spider.rs"),
+            "allprojects.apply(\"com.example.conventions\");\n\
+             listeners.log(\"events.log\");\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("a-repositories.spider.rs"),
+            "repositories.add(\"company\", \"https://repo.example.com\");\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let scripts = user_init_scripts(home.path()).unwrap();
+        assert_eq!(
+            scripts,
+            [
+                dir.join("a-repositories.spider.rs"),
+                dir.join("b-conventions.spider.rs")
+            ]
+        );
+        let mut conventions = InitConventions::new();
+        for script in &scripts {
+            conventions
+                .evaluate(&ScriptSource::read(script).unwrap())
+                .unwrap();
+        }
+
+        let project = Project::new();
+        conventions.apply_to(&project).unwrap();
+        assert!(project.has_plugin("com.example.conventions"));
+        assert_eq!(project.repositories()[0].url(), "https://repo.example.com");
+        conventions
+            .listeners()
+            .emit(BuildEvent::BuildFinished { failure: None });
+        assert_eq!(
+            std::fs::read_to_string(dir.join("events.log")).unwrap(),
+            "build succeeded\n"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Locates the root directory and settings script of a build
+
+use crate::invocation::script::SETTINGS_SCRIPT;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How the root of a build was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// The settings script was given with `-c/--settings-file`
+    SettingsFile,
+    /// The settings script was searched for from the `-p/--project-dir` directory upwards
+    ProjectDir,
+    /// The settings script was searched for from the working directory upwards
+    WorkingDir,
+}
+
+/// The location of a build's root directory and settings script
+#[derive(Debug, Clone)]
+pub struct BuildLayout {
+    root_dir: PathBuf,
+    current_dir: PathBuf,
+    settings_script: Option<PathBuf>,
+    mode: LayoutMode,
+}
+
+impl BuildLayout {
+    /// Finds the build layout for an invocation started in `working_dir`.
+    ///
+    /// An explicit settings script takes precedence over an explicit project directory. Otherwise,
+    /// the nearest directory containing a settings script, starting from the project directory
+    /// or the working directory, is the root of the build. When no settings script is found, the
+    /// starting directory is the root.
+    pub fn discover(
+        working_dir: &Path,
+        project_dir: Option<&Path>,
+        settings_script: Option<&Path>,
+    ) -> io::Result<Self> {
+        if let Some(settings_script) = settings_script {
+            let settings_script = std::path::absolute(working_dir.join(settings_script))?;
+            if !settings_script.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("settings file {settings_script:?} does not exist"),
+                ));
+            }
+            let root_dir = settings_script
+                .parent()
+                .expect("a file always has a parent")
+                .to_path_buf();
+            let current_dir = match project_dir {
+                Some(project_dir) => existing_dir(&working_dir.join(project_dir))?,
+                None => root_dir.clone(),
+            };
+            return Ok(Self {
+                root_dir,
+                current_dir,
+                settings_script: Some(settings_script),
+                mode: LayoutMode::SettingsFile,
+            });
+        }
+
+        let (current_dir, mode) = match project_dir {
+            Some(project_dir) => (
+                existing_dir(&working_dir.join(project_dir))?,
+                LayoutMode::ProjectDir,
+            ),
+            None => (existing_dir(working_dir)?, LayoutMode::WorkingDir),
+        };
+        let settings_script = current_dir
+            .ancestors()
+            .map(|dir| dir.join(SETTINGS_SCRIPT))
+            .find(|script| script.is_file());
+        let root_dir = match &settings_script {
+            Some(script) => script.parent().expect("a file always has a parent"),
+            None => &current_dir,
+        }
+        .to_path_buf();
+        Ok(Self {
+            root_dir,
+            current_dir,
+            settings_script,
+            mode,
+        })
+    }
+
+    /// Gets the root directory of the build
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// Gets the directory the build was started from, either the project directory or the
+    /// working directory
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+
+    /// Gets the settings script of the build, if there is one
+    pub fn settings_script(&self) -> Option<&Path> {
+        self.settings_script.as_deref()
+    }
+
+    /// Gets how the root of the build was found
+    pub fn mode(&self) -> LayoutMode {
+        self.mode
+    }
+}
+
+/// Makes `dir` absolute, checking that it is an existing directory
+fn existing_dir(dir: &Path) -> io::Result<PathBuf> {
+    let dir = std::path::absolute(dir)?;
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("project directory {dir:?} does not exist"),
+        ));
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_from_subproject() {
+        let root = tempfile::tempdir().unwrap();
+        let sub = root.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(root.path().join(SETTINGS_SCRIPT), "").unwrap();
+
+        let layout = BuildLayout::discover(&sub, None, None).unwrap();
+        assert_eq!(layout.root_dir(), root.path());
+        assert_eq!(layout.current_dir(), sub);
+        assert_eq!(layout.mode(), LayoutMode::WorkingDir);
+
+        let layout = BuildLayout::discover(root.path(), Some(Path::new("sub")), None).unwrap();
+        assert_eq!(layout.root_dir(), root.path());
+        assert_eq!(layout.mode(), LayoutMode::ProjectDir);
+    }
+
+    #[test]
+    fn test_explicit_settings_file() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("custom.spider.rs"), "").unwrap();
+
+        let layout =
+            BuildLayout::discover(root.path(), None, Some(Path::new("custom.spider.rs"))).unwrap();
+        assert_eq!(layout.root_dir(), root.path());
+        assert_eq!(layout.mode(), LayoutMode::SettingsFile);
+        assert!(BuildLayout::discover(root.path(), None, Some(Path::new("missing.rs"))).is_err());
+    }
+
+    #[test]
+    fn test_no_settings() {
+        let root = tempfile::tempdir().unwrap();
+        let layout = BuildLayout::discover(root.path(), None, None).unwrap();
+        assert_eq!(layout.root_dir(), root.path());
+        assert!(layout.settings_script().is_none());
+    }
+}
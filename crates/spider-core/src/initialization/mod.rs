@@ -1 +1,5 @@
+//! Initialization of a build, before any project is configured
 
+pub mod init_script;
+pub mod layout;
+pub mod settings;
@@ -0,0 +1,430 @@
+//! The [`Settings`] of a build, produced by evaluating its settings script.
+//!
+//! The settings script names the root project, includes the other projects of the build and
+//! may move them out of their default directories. It may also include other builds, making a
+//! composite build:
+//!
+//! ```text
+//! rootProject.name = "mockProject";
+//! include(":app", ":libs:util");
+//! project(":libs").projectDir = "third-party";
+//! includeBuild("../library");
+//! ```
+
+use crate::beans::BeanProvider;
+use crate::error::{ErrorKind, Result};
+use crate::invocation::script::ScriptSource;
+use crate::lazy::provider::ProviderFactory;
+use crate::lifecycle::{BuildPhase, PhaseTracker};
+use crate::properties::BuildProperties;
+use crate::shared::{Shared, shared};
+use indexmap::IndexSet;
+use spider_rs_compiler::{Expr, PluginRequest, StatementKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Describes a project of the build before it is created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectDescriptor {
+    path: String,
+    name: String,
+    dir: PathBuf,
+}
+
+impl ProjectDescriptor {
+    /// Gets the path of the project, such as `:app`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Gets the name of the project
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the directory of the project
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+#[derive(Debug)]
+struct SettingsInner {
+    root_dir: PathBuf,
+    root_project_name: String,
+    projects: IndexSet<String>,
+    project_dirs: HashMap<String, PathBuf>,
+    included_builds: IndexSet<PathBuf>,
+    plugins: Vec<PluginRequest>,
+}
+
+impl SettingsInner {
+    /// Gets the directory of a project, the directory of its parent joined with its name unless
+    /// it was set
+    fn dir_of(&self, path: &str) -> PathBuf {
+        if let Some(dir) = self.project_dirs.get(path) {
+            return dir.clone();
+        }
+        match path.rsplit_once(':') {
+            Some(("", "")) | None => self.root_dir.clone(),
+            Some(("", name)) => self.root_dir.join(name),
+            Some((parent, name)) => self.dir_of(parent).join(name),
+        }
+    }
+
+    fn descriptor(&self, path: &str) -> ProjectDescriptor {
+        let name = match path {
+            ":" => self.root_project_name.clone(),
+            path => path.rsplit(':').next().unwrap_or_default().to_string(),
+        };
+        ProjectDescriptor {
+            path: path.to_string(),
+            name,
+            dir: self.dir_of(path),
+        }
+    }
+}
+
+/// The settings of a build, which decide the projects it is made of.
+///
+/// The settings are also the bean scope of the settings phase, providing themselves and a
+/// [`ProviderFactory`] to the objects created while the settings script is evaluated.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    inner: Shared<SettingsInner>,
+    provider_factory: ProviderFactory,
+    phase: PhaseTracker,
+}
+
+impl Settings {
+    /// Creates the settings of the build in `root_dir`, with only a root project named after the
+    /// directory
+    pub fn new<P: AsRef<Path>>(root_dir: P) -> Self {
+        Self::with_properties(
+            root_dir,
+            shared(BuildProperties::new()),
+            PhaseTracker::new(),
+        )
+    }
+
+    /// Creates settings reading build properties from `properties`, which can be changed while
+    /// the build tracked by `phase` is being initialized
+    pub(crate) fn with_properties<P: AsRef<Path>>(
+        root_dir: P,
+        properties: Shared<BuildProperties>,
+        phase: PhaseTracker,
+    ) -> Self {
+        let root_dir = root_dir.as_ref().to_path_buf();
+        let root_project_name = root_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "root".to_string());
+        Self {
+            inner: shared(SettingsInner {
+                root_dir,
+                root_project_name,
+                projects: IndexSet::new(),
+                project_dirs: HashMap::new(),
+                included_builds: IndexSet::new(),
+                plugins: vec![],
+            }),
+            provider_factory: ProviderFactory::new(properties),
+            phase,
+        }
+    }
+
+    /// Gets the provider factory of the settings
+    pub fn providers(&self) -> ProviderFactory {
+        self.provider_factory.clone()
+    }
+
+    /// Gets the root directory of the build
+    pub async fn root_dir(&self) -> PathBuf {
+        self.inner.read().await.root_dir.clone()
+    }
+
+    /// Gets the name of the root project
+    pub async fn root_project_name(&self) -> String {
+        self.inner.read().await.root_project_name.clone()
+    }
+
+    /// Sets the name of the root project
+    pub async fn set_root_project_name<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_initializing("set the name of the root project")?;
+        self.inner.write().await.root_project_name = name.as_ref().to_string();
+        Ok(())
+    }
+
+    /// Checks that the settings can still be changed, which they can't once projects are being
+    /// configured
+    fn check_initializing(&self, action: &str) -> std::result::Result<(), ErrorKind> {
+        self.phase.check(action, BuildPhase::Initialization)
+    }
+
+    /// Includes projects in the build by their paths. The parents of a project are included
+    /// with it, so including `:a:b` also includes `:a`.
+    pub async fn include<I, S>(&self, paths: I) -> std::result::Result<(), ErrorKind>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.check_initializing("include projects")?;
+        let mut inner = self.inner.write().await;
+        for path in paths {
+            let path = normalize_path(path.as_ref())?;
+            let mut parent = String::new();
+            for segment in path[1..].split(':') {
+                parent.push(':');
+                parent.push_str(segment);
+                inner.projects.insert(parent.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the descriptor of a project of the build
+    pub async fn project<S: AsRef<str>>(&self, path: S) -> Option<ProjectDescriptor> {
+        let path = normalize_path(path.as_ref()).ok()?;
+        let inner = self.inner.read().await;
+        (path == ":" || inner.projects.contains(&path)).then(|| inner.descriptor(&path))
+    }
+
+    /// Sets the directory of an included project. Relative directories are resolved against the
+    /// root directory of the build, which is always the directory of the root project.
+    pub async fn set_project_dir<S: AsRef<str>, P: AsRef<Path>>(
+        &self,
+        path: S,
+        dir: P,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_initializing("set the directory of a project")?;
+        let path = normalize_path(path.as_ref())?;
+        let mut inner = self.inner.write().await;
+        if !inner.projects.contains(&path) {
+            return Err(ErrorKind::ProjectNotFound {
+                path,
+                suggestions: vec![],
+            });
+        }
+        let dir = inner.root_dir.join(dir);
+        inner.project_dirs.insert(path, dir);
+        Ok(())
+    }
+
+    /// Gets every project of the build, the root project first and then in the order they were
+    /// included
+    pub async fn projects(&self) -> Vec<ProjectDescriptor> {
+        let inner = self.inner.read().await;
+        std::iter::once(":")
+            .chain(inner.projects.iter().map(String::as_str))
+            .map(|path| inner.descriptor(path))
+            .collect()
+    }
+
+    /// Includes another build in this one, making a composite build. Relative directories are
+    /// resolved against the root directory of the build.
+    pub async fn include_build<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_initializing("include a build")?;
+        let mut inner = self.inner.write().await;
+        let dir = inner.root_dir.join(dir);
+        inner.included_builds.insert(dir);
+        Ok(())
+    }
+
+    /// Gets the root directories of the builds included in this one, in the order they were
+    /// included
+    pub async fn included_builds(&self) -> Vec<PathBuf> {
+        self.inner
+            .read()
+            .await
+            .included_builds
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Gets the plugins requested by the settings script
+    pub async fn plugins(&self) -> Vec<PluginRequest> {
+        self.inner.read().await.plugins.clone()
+    }
+
+    /// Evaluates a settings script, applying it to these settings
+    pub async fn evaluate(&self, script: &ScriptSource) -> Result<()> {
+        for statement in script.compile()?.statements() {
+            self.evaluate_statement(&statement.kind)
+                .await
+                .map_err(|message| ErrorKind::ScriptEvaluation {
+                    path: script.path().to_path_buf(),
+                    line: statement.line,
+                    message,
+                })?;
+        }
+        Ok(())
+    }
+
+    async fn evaluate_statement(
+        &self,
+        statement: &StatementKind,
+    ) -> std::result::Result<(), String> {
+        match statement {
+            StatementKind::Plugins(plugins) => {
+                self.inner
+                    .write()
+                    .await
+                    .plugins
+                    .extend(plugins.iter().cloned());
+                Ok(())
+            }
+            StatementKind::Assign {
+                target: Expr::Field { base, name },
+                value,
+            } => match (&**base, name.as_str()) {
+                (Expr::Ident(base), "name") if base == "rootProject" => self
+                    .set_root_project_name(string_value(value)?)
+                    .await
+                    .map_err(|e| e.to_string()),
+                (Expr::Call { function, args }, "projectDir") if function == "project" => {
+                    let [path] = args.as_slice() else {
+                        return Err("project() takes the path of a project".to_string());
+                    };
+                    self.set_project_dir(string_value(path)?, string_value(value)?)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+                _ => Err(format!("unknown settings property `{}.{name}`", base)),
+            },
+            StatementKind::Expr(Expr::Call { function, args }) if function == "include" => {
+                let paths = args
+                    .iter()
+                    .map(string_value)
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                self.include(paths).await.map_err(|e| e.to_string())
+            }
+            StatementKind::Expr(Expr::Call { function, args }) if function == "includeBuild" => {
+                let [dir] = args.as_slice() else {
+                    return Err("includeBuild() takes the directory of a build".to_string());
+                };
+                self.include_build(string_value(dir)?)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            StatementKind::Assign { target, .. } => {
+                Err(format!("`{target}` can't be assigned in a settings script"))
+            }
+            StatementKind::Expr(expr) => {
+                Err(format!("`{expr}` is not supported in a settings script"))
+            }
+        }
+    }
+}
+
+impl BeanProvider<Settings> for Settings {
+    fn get_bean(&self) -> Settings {
+        self.clone()
+    }
+}
+
+impl BeanProvider<ProviderFactory> for Settings {
+    fn get_bean(&self) -> ProviderFactory {
+        self.providers()
+    }
+}
+
+/// Gets the value of a string literal in a script
+fn string_value(expr: &Expr) -> std::result::Result<&str, String> {
+    expr.as_str()
+        .ok_or_else(|| format!("expected a string, found `{expr}`"))
+}
+
+/// Makes a project path absolute, checking that none of its segments is empty
+fn normalize_path(path: &str) -> std::result::Result<String, ErrorKind> {
+    let invalid = || ErrorKind::InvalidProjectPath {
+        path: path.to_string(),
+    };
+    match path {
+        "" => Err(invalid()),
+        ":" => Ok(":".to_string()),
+        path => {
+            let relative = path.strip_prefix(':').unwrap_or(path);
+            if relative.split(':').any(str::is_empty) {
+                return Err(invalid());
+            }
+            Ok(format!(":{relative}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_evaluate_settings_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.spider.rs");
+        std::fs::write(
+            &path,
+            "plugins! {\n    id \"settings\" version \"1.0.0\",\n}\n\n\
+             rootProject.name = \"mockProject\";\n\
+             include(\":a\", \":a:b\", \"c\");\n\
+             project(\":a\").projectDir = \"libs/a\";\n\
+             includeBuild(\"../lib\");\n",
+        )
+        .unwrap();
+        let settings = Settings::new(dir.path());
+        settings
+            .evaluate(&ScriptSource::read(&path).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(settings.plugins().await[0].id, "settings");
+        assert_eq!(
+            settings.included_builds().await,
+            [dir.path().join("../lib")]
+        );
+        let projects = settings.projects().await;
+        let summary = projects
+            .iter()
+            .map(|project| (project.path(), project.name(), project.dir()))
+            .collect::<Vec<_>>();
+        let root = dir.path();
+        assert_eq!(
+            summary,
+            [
+                (":", "mockProject", root),
+                (":a", "a", &*root.join("libs/a")),
+                (":a:b", "b", &*root.join("libs/a/b")),
+                (":c", "c", &*root.join("c")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evaluation_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.spider.rs");
+        std::fs::write(
+            &path,
+            "include(\":a\");\nproject(\":b\").projectDir = \"b\";\n",
+        )
+        .unwrap();
+        let settings = Settings::new(dir.path());
+        let error = settings
+            .evaluate(&ScriptSource::read(&path).unwrap())
+            .await
+            .unwrap_err();
+        let ErrorKind::ScriptEvaluation { line, .. } = error.kind else {
+            panic!("expected an evaluation error, got {:?}", error.kind)
+        };
+        assert_eq!(line, 2);
+        assert!(matches!(
+            settings.include(["::a"]).await,
+            Err(ErrorKind::InvalidProjectPath { .. })
+        ));
+    }
+}
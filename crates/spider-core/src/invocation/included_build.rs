@@ -0,0 +1,90 @@
+//! Builds included in a composite build with `includeBuild`
+
+use crate::error::{ErrorKind, Result};
+use crate::initialization::settings::Settings;
+use crate::invocation::script::{SETTINGS_SCRIPT, ScriptCache, ScriptSource};
+use crate::lifecycle::PhaseTracker;
+use crate::project::Project;
+use crate::properties::BuildProperties;
+use crate::shared::Shared;
+use std::path::{Path, PathBuf};
+use tracing::Instrument;
+
+/// A build included in a composite build.
+///
+/// The projects of an included build keep a tree of their own, rooted at a project whose path is
+/// the name of the build, so `:library:jar` is the `jar` task of the root project of the included
+/// build `library`. The name of an included build is the name of its root project.
+#[derive(Debug)]
+pub struct IncludedBuild {
+    root_dir: PathBuf,
+    settings_script: Option<ScriptSource>,
+    settings: Settings,
+    root_project: Project,
+}
+
+impl IncludedBuild {
+    /// Loads the build in `root_dir`, evaluating its settings script and creating its projects
+    pub(crate) async fn load(
+        root_dir: &Path,
+        properties: Shared<BuildProperties>,
+        phase: PhaseTracker,
+        script_cache: &ScriptCache,
+    ) -> Result<Self> {
+        let not_found = || ErrorKind::IncludedBuildNotFound {
+            dir: root_dir.to_path_buf(),
+        };
+        let root_dir = std::fs::canonicalize(root_dir).map_err(|_| not_found())?;
+        if !root_dir.is_dir() {
+            return Err(not_found().into());
+        }
+        let settings = Settings::with_properties(&root_dir, properties.clone(), phase.clone());
+        let settings_script = script_cache
+            .read_if_exists(root_dir.join(SETTINGS_SCRIPT))
+            .await?;
+        if let Some(script) = &settings_script {
+            settings
+                .evaluate(script)
+                .instrument(tracing::trace_span!("evaluate settings", path = %root_dir.display()))
+                .await?;
+        }
+        let root_project = Project::included_root(
+            settings.root_project_name().await,
+            &root_dir,
+            properties,
+            phase,
+        );
+        root_project.build_tree(&settings.projects().await)?;
+        Ok(Self {
+            root_dir,
+            settings_script,
+            settings,
+            root_project,
+        })
+    }
+
+    /// Gets the name of this build
+    pub fn name(&self) -> String {
+        self.root_project.name()
+    }
+
+    /// Gets the root directory of this build
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// Gets the settings script of this build, if it has one
+    pub fn settings_script(&self) -> Option<&ScriptSource> {
+        self.settings_script.as_ref()
+    }
+
+    /// Gets the settings of this build
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Gets the root project of this build
+    pub fn root_project(&self) -> &Project {
+        &self.root_project
+    }
+}
@@ -1,4 +1,7 @@
 //! Structs and functions for invoking spider
 
 pub mod compiler;
+pub mod included_build;
+pub mod script;
 pub mod spider;
+pub mod start_parameter;
@@ -0,0 +1,150 @@
+//! Spider script sources
+
+use crate::error::ErrorKind;
+use crate::shared::{Shared, shared};
+use spider_rs_compiler::Script;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::Instrument;
+
+/// The file name of a settings script
+pub const SETTINGS_SCRIPT: &str = "settings.spider.rs";
+/// The file name of a build script
+pub const BUILD_SCRIPT: &str = "build.spider.rs";
+
+/// The source text of a spider script
+#[derive(Debug, Clone)]
+pub struct ScriptSource {
+    path: PathBuf,
+    text: String,
+}
+
+impl ScriptSource {
+    /// Reads a script from the given path
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            text,
+        })
+    }
+
+    /// Reads a script from the given path, if it exists
+    pub fn read_if_exists<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        let path = path.as_ref();
+        if std::fs::exists(path)? {
+            Self::read(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Gets the path this script was read from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the text of this script
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Compiles this script
+    pub fn compile(&self) -> Result<Script, ErrorKind> {
+        let _span = tracing::trace_span!("compile script", path = %self.path.display()).entered();
+        spider_rs_compiler::compile(&self.text).map_err(|source| ErrorKind::ScriptCompilation {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+/// Keeps scripts between builds, reading a script again only when it was modified
+#[derive(Debug, Clone)]
+pub struct ScriptCache {
+    scripts: Shared<HashMap<PathBuf, (SystemTime, ScriptSource)>>,
+}
+
+impl ScriptCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self {
+            scripts: shared(HashMap::new()),
+        }
+    }
+
+    /// Reads a script, or gets it from the cache if it wasn't modified since
+    pub async fn read<P: AsRef<Path>>(&self, path: P) -> io::Result<ScriptSource> {
+        let path = path.as_ref();
+        let span = tracing::trace_span!("read script", path = %path.display());
+        self.read_cached(path).instrument(span).await
+    }
+
+    async fn read_cached(&self, path: &Path) -> io::Result<ScriptSource> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        if let Some((cached_modified, script)) = self.scripts.read().await.get(path) {
+            if *cached_modified == modified {
+                return Ok(script.clone());
+            }
+        }
+        let script = ScriptSource::read(path)?;
+        self.scripts
+            .write()
+            .await
+            .insert(path.to_path_buf(), (modified, script.clone()));
+        Ok(script)
+    }
+
+    /// Reads a script if it exists, using the cache like [`read`](Self::read)
+    pub async fn read_if_exists<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Option<ScriptSource>> {
+        let path = path.as_ref();
+        if std::fs::exists(path)? {
+            self.read(path).await.map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Gets the number of cached scripts
+    pub async fn len(&self) -> usize {
+        self.scripts.read().await.len()
+    }
+
+    /// Whether no script is cached
+    pub async fn is_empty(&self) -> bool {
+        self.scripts.read().await.is_empty()
+    }
+}
+
+impl Default for ScriptCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache_reads_modified_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(BUILD_SCRIPT);
+        std::fs::write(&path, "first").unwrap();
+        let cache = ScriptCache::new();
+        assert_eq!(cache.read(&path).await.unwrap().text(), "first");
+
+        std::fs::write(&path, "second").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(cache.read(&path).await.unwrap().text(), "second");
+        assert_eq!(cache.len().await, 1);
+    }
+}
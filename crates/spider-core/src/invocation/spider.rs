@@ -1,15 +1,60 @@
+use crate::build_init;
+use crate::dependencies::{Dependency, DependencySubstitutions, ModuleId, ResolvedDependency};
+use crate::error::{ErrorKind, Result};
+use crate::events::{BuildEvent, BuildListener, BuildListeners, TaskOutcome};
+use crate::execution::executor::TaskExecutor;
+use crate::execution::graph::TaskGraph;
+use crate::fs::user_home_dir;
+use crate::fs::watch::FileWatchSet;
+use crate::initialization::init_script::{INIT_SCRIPTS_DIR, InitConventions, user_init_scripts};
+use crate::initialization::layout::{BuildLayout, LayoutMode};
+use crate::initialization::settings::Settings;
+use crate::invocation::included_build::IncludedBuild;
+use crate::invocation::script::{BUILD_SCRIPT, SETTINGS_SCRIPT, ScriptCache, ScriptSource};
+use crate::invocation::start_parameter::StartParameter;
+use crate::lifecycle::{BuildHooks, BuildPhase, PhaseTracker};
+use crate::project::Project;
+use crate::properties::{BuildProperties, PROPERTIES_FILE, PropertySource, read_properties_file};
+use crate::reporting::register_help_tasks;
+use crate::shared::{Shared, shared};
+use crate::task::Task;
+use crate::task::selection::{NameMatch, match_name, select_task_path_from};
+use crate::wrapper;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env::current_dir;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::Instrument;
 
+/// The project property enabling configuration on demand when set to `true`
+pub const CONFIGURE_ON_DEMAND_PROPERTY: &str = "spider.configureondemand";
+
+/// Where an invocation of spider was started, and how the build it runs was found
 #[derive(Debug)]
-struct SpiderInvocationDetails {
+pub struct SpiderInvocationDetails {
     cwd: PathBuf,
+    layout: BuildLayout,
 }
 
 impl SpiderInvocationDetails {
-    fn new(cwd: PathBuf) -> Self {
-        Self { cwd }
+    fn new(cwd: PathBuf, layout: BuildLayout) -> Self {
+        Self { cwd, layout }
+    }
+
+    /// Gets the working directory of the invocation
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// Gets the layout of the build
+    pub fn layout(&self) -> &BuildLayout {
+        &self.layout
+    }
+
+    /// Gets how the root of the build was found
+    pub fn mode(&self) -> LayoutMode {
+        self.layout.mode()
     }
 }
 
@@ -17,6 +62,21 @@ impl SpiderInvocationDetails {
 #[derive(Debug)]
 pub struct Spider {
     details: SpiderInvocationDetails,
+    user_home: Option<PathBuf>,
+    init_scripts: Vec<PathBuf>,
+    conventions: InitConventions,
+    settings_script: Option<ScriptSource>,
+    settings: Settings,
+    build_script: Option<ScriptSource>,
+    script_cache: ScriptCache,
+    properties: Shared<BuildProperties>,
+    listeners: BuildListeners,
+    phase: PhaseTracker,
+    hooks: BuildHooks,
+    root_project: Project,
+    included_builds: Vec<IncludedBuild>,
+    initialized: bool,
+    configured: HashSet<String>,
 }
 
 impl Default for Spider {
@@ -32,14 +92,705 @@ impl Spider {
         Self::in_path(path)
     }
 
+    /// Creates a new spider instance for the build enclosing `path`
     pub fn in_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         let path = path.as_ref();
-        if !std::fs::exists(path)? {
-            Err(io::ErrorKind::NotFound)?;
+        let layout = BuildLayout::discover(path, None, None)?;
+        Ok(Self::with_layout(path, layout))
+    }
+
+    /// Creates a new spider instance in the current directory of the given parameters, using their
+    /// project directory and settings file when set
+    pub fn with_start_parameter(parameters: &StartParameter) -> io::Result<Self> {
+        let path = match parameters.current_dir() {
+            Some(dir) => dir.to_path_buf(),
+            None => current_dir()?,
+        };
+        let layout =
+            BuildLayout::discover(&path, parameters.project_dir(), parameters.settings_file())?;
+        let mut spider = Self::with_layout(&path, layout);
+        spider.init_scripts = parameters
+            .init_scripts()
+            .iter()
+            .map(|script| path.join(script))
+            .collect();
+        if let Some(user_home) = parameters.user_home() {
+            spider.set_user_home(path.join(user_home));
+        }
+        Ok(spider)
+    }
+
+    fn with_layout(cwd: &Path, layout: BuildLayout) -> Self {
+        let properties = shared(BuildProperties::new());
+        let phase = PhaseTracker::new();
+        Spider {
+            settings: Settings::with_properties(
+                layout.root_dir(),
+                properties.clone(),
+                phase.clone(),
+            ),
+            root_project: Project::root(
+                "root",
+                layout.root_dir(),
+                properties.clone(),
+                phase.clone(),
+            ),
+            details: SpiderInvocationDetails::new(cwd.to_path_buf(), layout),
+            user_home: user_home_dir(),
+            init_scripts: vec![],
+            conventions: InitConventions::new(),
+            settings_script: None,
+            build_script: None,
+            script_cache: ScriptCache::new(),
+            properties,
+            listeners: BuildListeners::new(),
+            hooks: BuildHooks::new(phase.clone()),
+            phase,
+            included_builds: vec![],
+            initialized: false,
+            configured: HashSet::new(),
+        }
+    }
+
+    /// Gets the spider user home init scripts and properties are read from, if there is one
+    pub fn user_home(&self) -> Option<&Path> {
+        self.user_home.as_deref()
+    }
+
+    /// Reads init scripts and properties from another spider user home
+    pub fn set_user_home<P: AsRef<Path>>(&mut self, user_home: P) {
+        self.user_home = Some(user_home.as_ref().to_path_buf());
+    }
+
+    /// Gets the directory this invocation was started in
+    pub fn cwd(&self) -> &Path {
+        &self.details.cwd
+    }
+
+    /// Gets the details of this invocation
+    pub fn details(&self) -> &SpiderInvocationDetails {
+        &self.details
+    }
+
+    /// Gets the root directory of the build
+    pub fn root_dir(&self) -> &Path {
+        self.details.layout.root_dir()
+    }
+
+    /// Gets the settings of this build, evaluated from its settings script once loaded
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Gets the phase this build is in
+    pub fn phase(&self) -> BuildPhase {
+        self.phase.current()
+    }
+
+    /// Gets the hooks run as this build moves through its phases
+    pub fn hooks(&self) -> &BuildHooks {
+        &self.hooks
+    }
+
+    /// Gets the root project of this build
+    pub fn root_project(&self) -> &Project {
+        &self.root_project
+    }
+
+    /// Gets the conventions applied to every project, declared by init scripts once loaded
+    pub fn conventions(&self) -> &InitConventions {
+        &self.conventions
+    }
+
+    /// Gets the conventions applied to every project, to declare conventions in code before the
+    /// build is loaded
+    pub fn conventions_mut(&mut self) -> &mut InitConventions {
+        &mut self.conventions
+    }
+
+    /// Gets the builds included in this one, once loaded
+    pub fn included_builds(&self) -> &[IncludedBuild] {
+        &self.included_builds
+    }
+
+    /// Finds a project of this build, or of a build included in it
+    pub fn find_project<S: AsRef<str>>(&self, path: S) -> Option<Project> {
+        self.root_projects()
+            .iter()
+            .find_map(|root| root.find_project(path.as_ref()))
+    }
+
+    /// Gets the root project of this build, followed by the root projects of included builds
+    fn root_projects(&self) -> Vec<Project> {
+        std::iter::once(&self.root_project)
+            .chain(self.included_builds.iter().map(IncludedBuild::root_project))
+            .cloned()
+            .collect()
+    }
+
+    /// Gets every project of this build and of the builds included in it
+    fn all_projects(&self) -> Vec<Project> {
+        self.root_projects()
+            .iter()
+            .flat_map(Project::all_projects)
+            .collect()
+    }
+
+    /// Gets the project the build was started from: the project whose directory is the current
+    /// directory, or else the nearest project enclosing it
+    fn current_project(&self) -> Project {
+        let current_dir = self.details.layout.current_dir();
+        self.all_projects()
+            .into_iter()
+            .filter(|project| current_dir.starts_with(project.dir()))
+            .max_by_key(|project| project.dir().components().count())
+            .unwrap_or_else(|| self.root_project.clone())
+    }
+
+    /// Gets the substitutions of modules produced by included builds. Each project of an included
+    /// build produces the module named after its group and name.
+    pub fn dependency_substitutions(&self) -> DependencySubstitutions {
+        let mut substitutions = DependencySubstitutions::new();
+        for build in &self.included_builds {
+            for project in build.root_project().all_projects() {
+                substitutions.substitute(
+                    ModuleId::new(project.group(), project.name()),
+                    project.path(),
+                );
+            }
+        }
+        substitutions
+    }
+
+    /// Resolves the dependencies of a project. Modules produced by an included build are
+    /// substituted with the project producing them, and project dependencies resolve to the
+    /// outputs of the task building the artifact of the project.
+    pub async fn resolve_dependencies(&self, project: &Project) -> Result<Vec<ResolvedDependency>> {
+        let substitutions = self.dependency_substitutions();
+        let mut resolved = vec![];
+        for dependency in project.dependencies().all().await {
+            resolved.push(match substitutions.apply(&dependency) {
+                Dependency::Module { id, version } => ResolvedDependency::Module { id, version },
+                Dependency::Project { path } => {
+                    let Some(target) = self.find_project(&path) else {
+                        return Err(ErrorKind::ProjectNotFound {
+                            path,
+                            suggestions: vec![],
+                        }
+                        .into());
+                    };
+                    resolve_project(&target).await
+                }
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// Gets the files a build of `graph` depends on: the scripts of the build, and the inputs of
+    /// every task in the graph
+    pub async fn watched_paths(&self, graph: &TaskGraph) -> FileWatchSet {
+        let mut paths = graph.inputs(self.root_dir()).await;
+        paths.extend(self.script_paths());
+        paths
+    }
+
+    /// Gets the paths of the scripts of the build, whether they exist or not: the init scripts
+    /// and the init script directory of the user home, the settings scripts of the build and of
+    /// included builds, the build script of every project, and the properties files of the user
+    /// home and of every project
+    pub fn script_paths(&self) -> Vec<PathBuf> {
+        let settings_script = match self.details.layout.settings_script() {
+            Some(path) => path.to_path_buf(),
+            None => self.root_dir().join(SETTINGS_SCRIPT),
+        };
+        let mut paths = self.init_scripts.clone();
+        if let Some(user_home) = &self.user_home {
+            paths.push(user_home.join(INIT_SCRIPTS_DIR));
+            paths.extend(user_init_scripts(user_home).unwrap_or_default());
+            paths.push(user_home.join(PROPERTIES_FILE));
+        }
+        paths.push(settings_script);
+        for build in &self.included_builds {
+            paths.push(build.root_dir().join(SETTINGS_SCRIPT));
+        }
+        for project in self.all_projects() {
+            paths.push(project.dir().join(BUILD_SCRIPT));
+            paths.push(project.dir().join(PROPERTIES_FILE));
+        }
+        paths
+    }
+
+    /// Adds a listener notified of the events of every build run by this instance
+    pub fn add_listener<L: BuildListener + 'static>(&mut self, listener: L) {
+        self.listeners.add(listener);
+    }
+
+    /// Sets the cache scripts are read through, which may be shared with other builds
+    pub fn set_script_cache(&mut self, script_cache: ScriptCache) {
+        self.script_cache = script_cache;
+    }
+
+    /// Gets the settings script, if one has been loaded
+    pub fn settings_script(&self) -> Option<&ScriptSource> {
+        self.settings_script.as_ref()
+    }
+
+    /// Gets the build script of the root project, if one has been loaded
+    pub fn build_script(&self) -> Option<&ScriptSource> {
+        self.build_script.as_ref()
+    }
+
+    /// Evaluates the init scripts, then loads the settings and build scripts from the root of the
+    /// build and registers the built-in tasks of its projects.
+    ///
+    /// The build is initialized until its projects are known, and then moves to the configuration
+    /// phase, running the `before_project` and `after_evaluate` hooks around the configuration of
+    /// each project.
+    pub async fn load(&mut self) -> Result<()> {
+        self.initialize().await?;
+        self.configure_projects(self.all_projects()).await
+    }
+
+    /// Loads the build for the given parameters, applying their properties.
+    ///
+    /// When configuring on demand, either with the start parameter or the
+    /// `spider.configureondemand` project property, only the root project, the projects of the
+    /// requested tasks and the projects they depend on are configured.
+    pub async fn load_for(&mut self, parameters: &StartParameter) -> Result<()> {
+        {
+            let mut properties = self.properties.write().await;
+            properties.set_project_properties(
+                PropertySource::CommandLine,
+                parameters.project_properties().clone(),
+            );
+            properties.set_system_properties(parameters.system_properties().clone());
+        }
+        self.initialize().await?;
+        let on_demand = parameters.is_configure_on_demand()
+            || self
+                .properties
+                .read()
+                .await
+                .project_property(CONFIGURE_ON_DEMAND_PROPERTY)
+                == Some("true");
+        if !on_demand {
+            return self.load().await;
+        }
+        let mut projects = vec![self.root_project.clone()];
+        projects.extend(
+            parameters
+                .task_names()
+                .iter()
+                .filter(|name| !name.starts_with("--"))
+                .filter_map(|name| self.requested_project(name)),
+        );
+        self.configure_projects(projects).await
+    }
+
+    /// Finds the project of a requested task, resolving abbreviated project names like task
+    /// selection does. Names that don't select a project are left for task selection to report.
+    fn requested_project(&self, task_name: &str) -> Option<Project> {
+        let segments = task_name
+            .trim_start_matches(':')
+            .split(':')
+            .collect::<Vec<_>>();
+        let (_, project_segments) = segments.split_last()?;
+        let mut project = if task_name.starts_with(':') {
+            self.root_project.clone()
+        } else {
+            self.current_project()
+        };
+        for (i, segment) in project_segments.iter().enumerate() {
+            let mut candidates = project.children();
+            if i == 0 && project.path() == self.root_project.path() {
+                candidates.extend(
+                    self.included_builds
+                        .iter()
+                        .map(|build| build.root_project().clone()),
+                );
+            }
+            let names = candidates.iter().map(Project::name).collect::<Vec<_>>();
+            let NameMatch::Found(name) = match_name(segment, names.iter().map(String::as_str))
+            else {
+                return None;
+            };
+            project = candidates.into_iter().find(|c| c.name() == name)?;
+        }
+        Some(project)
+    }
+
+    /// Configures the given projects and the projects they need: their parents, the projects
+    /// they depend on, and the projects of tasks their tasks depend on. Projects that were
+    /// already configured are skipped.
+    async fn configure_projects(&mut self, projects: Vec<Project>) -> Result<()> {
+        self.phase.enter(BuildPhase::Configuration);
+        let substitutions = self.dependency_substitutions();
+        let mut pending = VecDeque::from(projects);
+        while let Some(mut project) = pending.pop_front() {
+            if self.configured.contains(project.path()) {
+                continue;
+            }
+            if let Some(parent) = project
+                .parent()
+                .filter(|parent| !self.configured.contains(parent.path()))
+            {
+                pending.push_front(project);
+                pending.push_front(parent);
+                continue;
+            }
+            let path = project.path().to_string();
+            self.hooks.run_before_project(&mut project);
+            self.configure_project(&project)
+                .instrument(tracing::trace_span!("configure project", path = %path))
+                .await?;
+            self.hooks.run_after_evaluate(&mut project);
+            self.configured.insert(path.clone());
+            self.listeners.emit(BuildEvent::ProjectConfigured { path });
+
+            for dependency in project.dependencies().all().await {
+                if let Dependency::Project { path } = substitutions.apply(&dependency) {
+                    pending.extend(self.find_project(path));
+                }
+            }
+            for task in project.tasks().await.all().await {
+                for dependency in task.dependencies().await {
+                    let project_path = match dependency.rsplit_once(':') {
+                        Some(("", _)) => ":",
+                        Some((project_path, _)) => project_path,
+                        None => continue,
+                    };
+                    pending.extend(self.find_project(project_path));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the properties files, evaluates the init scripts and the settings, and creates the
+    /// projects of the build and of the builds it includes
+    async fn initialize(&mut self) -> Result<()> {
+        if self.initialized {
+            return Ok(());
         }
-        Ok(Spider {
-            details: SpiderInvocationDetails::new(path.to_path_buf()),
-        })
+        tracing::debug!(
+            "build root {:?} found from {:?}",
+            self.root_dir(),
+            self.details.mode()
+        );
+        self.read_properties_files().await?;
+        self.evaluate_init_scripts()
+            .instrument(tracing::trace_span!("evaluate init scripts"))
+            .await?;
+        self.evaluate_settings()
+            .instrument(tracing::trace_span!("evaluate settings"))
+            .await?;
+        self.listeners.emit(BuildEvent::SettingsEvaluated {
+            root_dir: self.root_dir().to_path_buf(),
+            settings_script: self.details.layout.settings_script().map(Path::to_path_buf),
+        });
+        self.root_project
+            .build_tree(&self.settings.projects().await)?;
+        self.load_included_builds().await?;
+        self.read_project_properties_files().await?;
+        self.initialized = true;
+        Ok(())
+    }
+
+    /// Reads the properties files of the spider user home and of the root of the build
+    async fn read_properties_files(&self) -> Result<()> {
+        let user_home = match &self.user_home {
+            Some(dir) => read_properties_file(dir)?,
+            None => HashMap::new(),
+        };
+        let root = read_properties_file(self.root_dir())?;
+        let mut properties = self.properties.write().await;
+        properties.set_project_properties(PropertySource::UserHome, user_home);
+        properties.set_project_properties(PropertySource::Project, root);
+        Ok(())
+    }
+
+    /// Reads the properties files in the directories of projects other than the root project
+    async fn read_project_properties_files(&self) -> Result<()> {
+        for project in self.all_projects() {
+            if project == self.root_project {
+                continue;
+            }
+            let project_properties = read_properties_file(project.dir())?;
+            self.properties
+                .write()
+                .await
+                .set_project_dir_properties(project.path(), project_properties);
+        }
+        Ok(())
+    }
+
+    /// Evaluates the init scripts given for this invocation, then those of the user home, and
+    /// adds the listeners they declare to the build
+    async fn evaluate_init_scripts(&mut self) -> Result<()> {
+        let mut paths = self.init_scripts.clone();
+        if let Some(user_home) = &self.user_home {
+            paths.extend(user_init_scripts(user_home)?);
+        }
+        for path in paths {
+            tracing::debug!("evaluating init script {path:?}");
+            let script = self.script_cache.read(&path).await?;
+            self.conventions.evaluate(&script)?;
+        }
+        self.listeners.add(self.conventions.listeners().clone());
+        Ok(())
+    }
+
+    /// Evaluates the settings script, if the build has one
+    async fn evaluate_settings(&mut self) -> Result<()> {
+        self.settings_script = match self.details.layout.settings_script() {
+            Some(path) => Some(self.script_cache.read(path).await?),
+            None => None,
+        };
+        if let Some(script) = &self.settings_script {
+            self.settings.evaluate(script).await?;
+        }
+        Ok(())
+    }
+
+    /// Loads the builds included by the settings, and the builds those include in turn. Every
+    /// build is included once, and the names of included builds must be unique.
+    async fn load_included_builds(&mut self) -> Result<()> {
+        let mut pending = VecDeque::from(self.settings.included_builds().await);
+        let mut seen = HashSet::from([std::fs::canonicalize(self.root_dir())?]);
+        while let Some(dir) = pending.pop_front() {
+            // a missing directory is reported by loading it
+            let dir = std::fs::canonicalize(&dir).unwrap_or(dir);
+            if !seen.insert(dir.clone()) {
+                continue;
+            }
+            let build = IncludedBuild::load(
+                &dir,
+                self.properties.clone(),
+                self.phase.clone(),
+                &self.script_cache,
+            )
+            .await?;
+            let name = build.name();
+            if self.find_project(format!(":{name}")).is_some() {
+                return Err(ErrorKind::BuildNameConflict { name }.into());
+            }
+            tracing::debug!("included build {name:?} from {:?}", build.root_dir());
+            pending.extend(build.settings().included_builds().await);
+            self.properties
+                .write()
+                .await
+                .set_included_build_root(build.root_project().path());
+            self.included_builds.push(build);
+        }
+        Ok(())
+    }
+
+    /// Applies the conventions of init scripts and the project properties to a project, reads its
+    /// build script and registers its built-in tasks. The build setup tasks are only registered
+    /// in the root project.
+    async fn configure_project(&mut self, project: &Project) -> Result<()> {
+        self.conventions.apply_to(project)?;
+        let project_properties = self
+            .properties
+            .read()
+            .await
+            .project_properties_in(project.path());
+        project.set_project_properties(project_properties);
+        let build_script = self
+            .script_cache
+            .read_if_exists(project.dir().join(BUILD_SCRIPT))
+            .await?;
+        register_help_tasks(project, &self.root_projects()).await?;
+        if *project == self.root_project {
+            self.build_script = build_script;
+            build_init::register(project, self.details.layout.current_dir()).await?;
+            wrapper::register(project, self.root_dir()).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs a build with the given parameters.
+    ///
+    /// Requested tasks are executed in the order they were given after the tasks they depend on,
+    /// each task at most once.
+    ///
+    /// A spider can run its build more than once, as the daemon does. Every run moves the build
+    /// back to the configuration phase, keeping the projects earlier runs configured, and clears
+    /// the options earlier runs gave to tasks.
+    pub async fn run(&mut self, parameters: &StartParameter) -> Result<()> {
+        let result = self.run_build(parameters).await;
+        self.phase.enter(BuildPhase::Finished);
+        self.hooks.run_build_finished(&result);
+        self.listeners.emit(BuildEvent::BuildFinished {
+            failure: result.as_ref().err().map(|error| error.kind.to_string()),
+        });
+        result
+    }
+
+    async fn run_build(&mut self, parameters: &StartParameter) -> Result<()> {
+        for task in self.all_tasks().await.into_values() {
+            task.reset().await;
+        }
+        self.load_for(parameters).await?;
+        let graph = self.task_graph(parameters).await?;
+        self.hooks.run_task_graph_ready(&graph);
+        self.listeners.emit(BuildEvent::TaskGraphReady {
+            tasks: graph.paths().map(str::to_string).collect(),
+        });
+        self.phase.enter(BuildPhase::Execution);
+        if parameters.is_dry_run() {
+            for path in graph.paths() {
+                self.listeners.emit(BuildEvent::TaskFinished {
+                    path: path.to_string(),
+                    outcome: TaskOutcome::Skipped,
+                    duration: Duration::ZERO,
+                });
+            }
+            return Ok(());
+        }
+        let mut executor = TaskExecutor::new();
+        executor.set_continue_on_failure(parameters.is_continue_on_failure());
+        executor.set_listeners(self.listeners.clone());
+        executor.execute(&graph, &self.root_projects()).await
+    }
+
+    /// Computes the graph of tasks to execute for the given parameters
+    pub async fn task_graph(&self, parameters: &StartParameter) -> Result<TaskGraph> {
+        let available = self.all_tasks().await;
+        let requested = self
+            .select_tasks(parameters.task_names(), &available)
+            .await?;
+        let current_project = self.current_project();
+        let excluded = parameters
+            .excluded_task_names()
+            .iter()
+            .map(|name| {
+                select_task_path_from(
+                    name,
+                    current_project.path(),
+                    available.keys().map(String::as_str),
+                )
+            })
+            .collect::<std::result::Result<HashSet<_>, _>>()?;
+        let inferred = self.artifact_dependencies(&available).await?;
+        TaskGraph::with_inferred_dependencies(&requested, &available, &excluded, &inferred).await
+    }
+
+    /// Gets the tasks building the artifacts of the projects each task consuming dependencies
+    /// needs, by the path of the consuming task
+    async fn artifact_dependencies(
+        &self,
+        available: &HashMap<String, Task>,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let mut inferred = HashMap::new();
+        for (path, task) in available {
+            if !task.consumes_dependencies().await {
+                continue;
+            }
+            let Some(project) = self.find_project(task.project_path().await) else {
+                continue;
+            };
+            let tasks = self
+                .resolve_dependencies(&project)
+                .await?
+                .into_iter()
+                .filter_map(|dependency| match dependency {
+                    ResolvedDependency::Project { task, .. } => task,
+                    ResolvedDependency::Module { .. } => None,
+                })
+                .collect();
+            inferred.insert(path.clone(), tasks);
+        }
+        Ok(inferred)
+    }
+
+    /// Loads the build and gets the paths of all of its tasks, sorted
+    pub async fn task_paths(&mut self) -> Result<Vec<String>> {
+        self.load().await?;
+        let mut paths = self.all_tasks().await.into_keys().collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Gets every task in the build by its path
+    async fn all_tasks(&self) -> HashMap<String, Task> {
+        let mut tasks = HashMap::new();
+        for project in self.all_projects() {
+            for task in project.tasks().await.all().await {
+                tasks.insert(task.path().await, task);
+            }
+        }
+        tasks
+    }
+
+    /// Selects the requested tasks from command line arguments.
+    ///
+    /// Arguments starting with `--` set an option of the task named before them, either as
+    /// `--option=value` or `--option value` for options that take a value. Task names without
+    /// a leading `:` are relative to the project the build was started from, and may be
+    /// abbreviated.
+    async fn select_tasks(
+        &self,
+        args: &[String],
+        available: &HashMap<String, Task>,
+    ) -> Result<Vec<Task>> {
+        let current_project = self.current_project();
+        let mut tasks = vec![];
+        let mut current: Option<Task> = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(option) = arg.strip_prefix("--") {
+                let Some(task) = &current else {
+                    return Err(ErrorKind::UnknownOption {
+                        option: option.to_string(),
+                    }
+                    .into());
+                };
+                let (name, value) = match option.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None if task.option(option).await.is_some_and(|o| o.takes_value()) => {
+                        (option, args.next().cloned())
+                    }
+                    None => (option, None),
+                };
+                task.set_option(name, value).await?;
+            } else {
+                let path = select_task_path_from(
+                    arg,
+                    current_project.path(),
+                    available.keys().map(String::as_str),
+                )?;
+                let task = available[&path].clone();
+                tasks.push(task.clone());
+                current = Some(task);
+            }
+        }
+        Ok(tasks)
+    }
+}
+
+/// Resolves a dependency on a project to the outputs of the task building its artifact
+async fn resolve_project(project: &Project) -> ResolvedDependency {
+    let task = match project.artifact_task() {
+        Some(name) => project.tasks().await.find(name).await,
+        None => None,
+    };
+    let (task, files) = match task {
+        Some(task) => {
+            let files = task
+                .outputs()
+                .await
+                .iter()
+                .map(|output| project.dir().join(output))
+                .collect();
+            (Some(task.path().await), files)
+        }
+        None => (None, vec![]),
+    };
+    ResolvedDependency::Project {
+        path: project.path().to_string(),
+        task,
+        files,
     }
 }
 
@@ -55,3 +806,380 @@ impl SpiderAware for Spider {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lazy::provider::Provider;
+    use crate::task::options::TaskOption;
+    use crate::task::{TaskError, from_fn};
+    use std::sync::Arc;
+
+    /// Creates a spider for the build in `dir`, with a user home of its own
+    fn spider_in(dir: &Path) -> Spider {
+        let mut spider = Spider::in_path(dir).expect("could not create spider");
+        spider.set_user_home(dir.join(".spider"));
+        spider
+    }
+
+    #[tokio::test]
+    async fn test_run_requested_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spider = spider_in(dir.path());
+        let task = spider
+            .root_project()
+            .tasks()
+            .await
+            .register("hello")
+            .await
+            .unwrap();
+        task.do_last(from_fn(|_, _| async { Ok(()) }))
+            .await
+            .unwrap();
+
+        let mut parameters = StartParameter::new();
+        parameters.set_task_names(["hello", ":hello"]);
+        spider.run(&parameters).await.expect("build should pass");
+
+        parameters.set_task_names(["missing"]);
+        let error = spider.run(&parameters).await.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::TaskNotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spider = spider_in(dir.path());
+        let calls = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let hooks = spider.hooks();
+        let log = calls.clone();
+        hooks
+            .before_project(move |project: &mut Project| {
+                log.lock()
+                    .unwrap()
+                    .push(format!("before {}", project.path()));
+            })
+            .unwrap();
+        let log = calls.clone();
+        hooks
+            .after_evaluate(move |project: &mut Project| {
+                log.lock()
+                    .unwrap()
+                    .push(format!("after {}", project.path()));
+            })
+            .unwrap();
+        let log = calls.clone();
+        hooks
+            .task_graph()
+            .when_ready(move |graph: &TaskGraph| {
+                let tasks = graph.paths().collect::<Vec<_>>().join(" ");
+                log.lock().unwrap().push(format!("graph {tasks}"));
+            })
+            .unwrap();
+        let log = calls.clone();
+        hooks
+            .build_finished(move |result: &Result<()>| {
+                log.lock()
+                    .unwrap()
+                    .push(format!("finished {}", result.is_ok()));
+            })
+            .unwrap();
+
+        let mut parameters = StartParameter::new();
+        parameters.set_task_names([":help"]);
+        spider.run(&parameters).await.expect("build should pass");
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["before :", "after :", "graph :help", "finished true"]
+        );
+        assert_eq!(spider.phase(), BuildPhase::Finished);
+
+        let error = spider
+            .root_project()
+            .tasks()
+            .await
+            .register("late")
+            .await
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::IllegalPhase { .. }));
+        assert!(spider.hooks().after_evaluate(|_: &mut Project| {}).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spider = spider_in(dir.path());
+        spider.load().await.unwrap();
+        let task = spider
+            .root_project()
+            .tasks()
+            .await
+            .register("greet")
+            .await
+            .unwrap();
+        task.add_option(TaskOption::flag("loud", "Greets loudly."))
+            .await
+            .unwrap();
+        let greetings = Arc::new(std::sync::Mutex::new(vec![]));
+        let greeted = greetings.clone();
+        task.do_last(from_fn(move |task: Task, _| {
+            let greeted = greeted.clone();
+            async move {
+                let loud = task
+                    .option("loud")
+                    .await
+                    .is_some_and(|option| option.value().is_some());
+                greeted.lock().unwrap().push(loud);
+                Ok(())
+            }
+        }))
+        .await
+        .unwrap();
+
+        for args in [&["greet", "--loud"][..], &["greet"]] {
+            let mut parameters = StartParameter::new();
+            parameters.set_task_names(args);
+            spider.run(&parameters).await.expect("build should pass");
+            assert_eq!(spider.phase(), BuildPhase::Finished);
+            assert!(matches!(
+                task.set_group("late").await,
+                Err(ErrorKind::IllegalPhase { .. })
+            ));
+        }
+        // the second run starts configured again, without the option of the first
+        assert_eq!(*greetings.lock().unwrap(), [true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spider = spider_in(dir.path());
+        let task = spider
+            .root_project()
+            .tasks()
+            .await
+            .register("fail")
+            .await
+            .unwrap();
+        task.do_last(from_fn(|_, _| async {
+            Err(TaskError::fail(ErrorKind::custom("should not run")))
+        }))
+        .await
+        .unwrap();
+
+        let mut parameters = StartParameter::new();
+        parameters.set_task_names(["fail"]);
+        parameters.set_dry_run(true);
+        let skipped = Arc::new(std::sync::Mutex::new(vec![]));
+        spider.add_listener({
+            let skipped = skipped.clone();
+            move |event: &BuildEvent| {
+                if let BuildEvent::TaskFinished {
+                    path,
+                    outcome: TaskOutcome::Skipped,
+                    ..
+                } = event
+                {
+                    skipped.lock().unwrap().push(path.clone());
+                }
+            }
+        });
+        spider.run(&parameters).await.expect("dry run should pass");
+        assert_eq!(*skipped.lock().unwrap(), [":fail"]);
+    }
+
+    #[tokio::test]
+    async fn test_composite_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app");
+        let library = dir.path().join("library");
+        std::fs::create_dir_all(library.join("util")).unwrap();
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(app.join(SETTINGS_SCRIPT), "includeBuild(\"../library\");\n").unwrap();
+        std::fs::write(library.join(SETTINGS_SCRIPT), "include(\":util\");\n").unwrap();
+        std::fs::write(app.join(PROPERTIES_FILE), "version=2.0\n").unwrap();
+
+        let mut spider = spider_in(&app);
+        let paths = spider.task_paths().await.unwrap();
+        assert!(paths.contains(&":library:util:tasks".to_string()));
+        assert_eq!(spider.included_builds()[0].name(), "library");
+
+        let util = spider.find_project(":library:util").unwrap();
+        let version = util.providers().project_property("version");
+        assert_eq!(version.try_get().await, None);
+        util.set_group("org.example").unwrap();
+        let jar = util.tasks().await.register("jar").await.unwrap();
+        jar.add_output("build/util.jar").await.unwrap();
+        util.set_artifact_task("jar").unwrap();
+        let dependencies = spider.root_project().dependencies();
+        for notation in ["org.example:util:1.0", "org.example:other:1.0"] {
+            dependencies
+                .add(Dependency::module(notation).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let resolved = spider
+            .resolve_dependencies(spider.root_project())
+            .await
+            .unwrap();
+        assert_eq!(
+            resolved[0],
+            ResolvedDependency::Project {
+                path: ":library:util".to_string(),
+                task: Some(":library:util:jar".to_string()),
+                files: vec![util.dir().join("build/util.jar")],
+            }
+        );
+        assert!(matches!(resolved[1], ResolvedDependency::Module { .. }));
+
+        let executed = Arc::new(std::sync::Mutex::new(vec![]));
+        let compile = spider
+            .root_project()
+            .tasks()
+            .await
+            .register("compile")
+            .await
+            .unwrap();
+        compile.set_consumes_dependencies(true).await.unwrap();
+        for task in [&jar, &compile] {
+            let executed = executed.clone();
+            task.do_last(from_fn(move |task: Task, _| {
+                let executed = executed.clone();
+                async move {
+                    let path = task.path().await;
+                    executed.lock().unwrap().push(path);
+                    Ok(())
+                }
+            }))
+            .await
+            .unwrap();
+        }
+        let mut parameters = StartParameter::new();
+        parameters.set_task_names(["compile"]);
+        spider.run(&parameters).await.expect("build should pass");
+        assert_eq!(*executed.lock().unwrap(), [":library:util:jar", ":compile"]);
+    }
+
+    #[tokio::test]
+    async fn test_script_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(SETTINGS_SCRIPT), "include(\":app\");\n").unwrap();
+        let mut spider = spider_in(dir.path());
+        spider.load().await.unwrap();
+
+        let paths = spider.script_paths();
+        assert!(paths.contains(&dir.path().join(SETTINGS_SCRIPT)));
+        assert!(paths.contains(&dir.path().join(BUILD_SCRIPT)));
+        assert!(paths.contains(&dir.path().join("app").join(BUILD_SCRIPT)));
+        assert!(paths.contains(&dir.path().join("app").join(PROPERTIES_FILE)));
+        let user_home = dir.path().join(".spider");
+        assert!(paths.contains(&user_home.join(INIT_SCRIPTS_DIR)));
+        assert!(paths.contains(&user_home.join(PROPERTIES_FILE)));
+    }
+
+    #[tokio::test]
+    async fn test_configure_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(SETTINGS_SCRIPT),
+            "include(\":app\");\ninclude(\":lib\");\ninclude(\":other\");\n\
+             include(\":nested:deep\");\n",
+        )
+        .unwrap();
+        let mut spider = spider_in(dir.path());
+        let configured = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let log = configured.clone();
+        spider
+            .hooks()
+            .after_evaluate(move |project: &mut Project| {
+                log.lock().unwrap().push(project.path().to_string());
+            })
+            .unwrap();
+
+        let mut parameters = StartParameter::new();
+        parameters.set_configure_on_demand(true);
+        parameters.set_task_names(["ap:tasks", ":nested:deep:tasks"]);
+        spider.initialize().await.unwrap();
+        spider
+            .find_project(":app")
+            .unwrap()
+            .dependencies()
+            .add(Dependency::project(":lib"))
+            .await
+            .unwrap();
+        spider.run(&parameters).await.expect("build should pass");
+        assert_eq!(
+            *configured.lock().unwrap(),
+            [":", ":app", ":nested", ":nested:deep", ":lib"]
+        );
+        assert!(spider.find_project(":other").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tasks_relative_to_current_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app");
+        std::fs::create_dir_all(app.join("src")).unwrap();
+        std::fs::write(
+            dir.path().join(SETTINGS_SCRIPT),
+            "include(\":app\");\ninclude(\":lib\");\n",
+        )
+        .unwrap();
+        let mut spider = Spider::in_path(app.join("src")).unwrap();
+        spider.set_user_home(dir.path().join(".spider"));
+        let configured = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let log = configured.clone();
+        spider
+            .hooks()
+            .after_evaluate(move |project: &mut Project| {
+                log.lock().unwrap().push(project.path().to_string());
+            })
+            .unwrap();
+
+        let mut parameters = StartParameter::new();
+        parameters.set_configure_on_demand(true);
+        parameters.set_task_names(["tasks"]);
+        spider.load_for(&parameters).await.unwrap();
+        assert_eq!(*configured.lock().unwrap(), [":", ":app"]);
+        let graph = spider.task_graph(&parameters).await.unwrap();
+        assert_eq!(graph.paths().collect::<Vec<_>>(), [":app:tasks"]);
+    }
+
+    #[tokio::test]
+    async fn test_properties_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        std::fs::write(dir.path().join(SETTINGS_SCRIPT), "include(\":a\");\n").unwrap();
+        std::fs::write(
+            dir.path().join(PROPERTIES_FILE),
+            "version=1.0\nfeature=off\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("a").join(PROPERTIES_FILE), "version=1.1\n").unwrap();
+        std::fs::create_dir(dir.path().join(".spider")).unwrap();
+        std::fs::write(
+            dir.path().join(".spider").join(PROPERTIES_FILE),
+            "owner=me\n",
+        )
+        .unwrap();
+
+        let mut spider = spider_in(dir.path());
+        let mut parameters = StartParameter::new();
+        parameters.set_project_property("feature", "on");
+        parameters.set_task_names(["help"]);
+        spider.run(&parameters).await.expect("build should pass");
+
+        let a = spider.find_project(":a").unwrap();
+        let version = a.providers().project_property("version");
+        assert_eq!(version.try_get().await.as_deref(), Some("1.1"));
+        let root_version = spider
+            .root_project()
+            .providers()
+            .project_property("version");
+        assert_eq!(root_version.try_get().await.as_deref(), Some("1.0"));
+        assert_eq!(a.extra().get::<String>("feature").unwrap(), "on");
+        assert_eq!(a.extra().get::<String>("owner").unwrap(), "me");
+        assert!(!a.extra().contains_key("feature"));
+    }
+}
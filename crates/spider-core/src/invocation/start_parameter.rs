@@ -0,0 +1,173 @@
+//! The parameters a spider build is started with
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Describes what a spider invocation should do
+#[derive(Debug, Default, Clone)]
+pub struct StartParameter {
+    task_names: Vec<String>,
+    excluded_task_names: Vec<String>,
+    dry_run: bool,
+    continue_on_failure: bool,
+    configure_on_demand: bool,
+    project_properties: HashMap<String, String>,
+    system_properties: HashMap<String, String>,
+    current_dir: Option<PathBuf>,
+    project_dir: Option<PathBuf>,
+    settings_file: Option<PathBuf>,
+    init_scripts: Vec<PathBuf>,
+    user_home: Option<PathBuf>,
+}
+
+impl StartParameter {
+    /// Creates a new, empty start parameter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tasks requested for this build, in the order they were given
+    pub fn set_task_names<I, S>(&mut self, task_names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.task_names = task_names
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect();
+    }
+
+    /// Gets the tasks requested for this build
+    pub fn task_names(&self) -> &[String] {
+        &self.task_names
+    }
+
+    /// Sets the tasks to leave out of the build
+    pub fn set_excluded_task_names<I, S>(&mut self, excluded_task_names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.excluded_task_names = excluded_task_names
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect();
+    }
+
+    /// Gets the tasks to leave out of the build
+    pub fn excluded_task_names(&self) -> &[String] {
+        &self.excluded_task_names
+    }
+
+    /// Sets whether the build only prints the tasks it would execute
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Whether the build only prints the tasks it would execute
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Sets whether to keep executing independent tasks after a task fails
+    pub fn set_continue_on_failure(&mut self, continue_on_failure: bool) {
+        self.continue_on_failure = continue_on_failure;
+    }
+
+    /// Whether to keep executing independent tasks after a task fails
+    pub fn is_continue_on_failure(&self) -> bool {
+        self.continue_on_failure
+    }
+
+    /// Sets whether to only configure the projects the requested tasks need
+    pub fn set_configure_on_demand(&mut self, configure_on_demand: bool) {
+        self.configure_on_demand = configure_on_demand;
+    }
+
+    /// Whether to only configure the projects the requested tasks need
+    pub fn is_configure_on_demand(&self) -> bool {
+        self.configure_on_demand
+    }
+
+    /// Sets a project property, as given with `-P`
+    pub fn set_project_property<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) {
+        self.project_properties
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+    }
+
+    /// Gets the project properties given on the command line
+    pub fn project_properties(&self) -> &HashMap<String, String> {
+        &self.project_properties
+    }
+
+    /// Sets a system property, as given with `-D`
+    pub fn set_system_property<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) {
+        self.system_properties
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+    }
+
+    /// Gets the system properties given on the command line
+    pub fn system_properties(&self) -> &HashMap<String, String> {
+        &self.system_properties
+    }
+
+    /// Sets the directory the build was invoked from, instead of the working directory of this
+    /// process
+    pub fn set_current_dir<P: AsRef<Path>>(&mut self, current_dir: Option<P>) {
+        self.current_dir = current_dir.map(|p| p.as_ref().to_path_buf());
+    }
+
+    /// Gets the directory the build was invoked from, if not the working directory
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    /// Sets the directory to start the build from instead of the working directory
+    pub fn set_project_dir<P: AsRef<Path>>(&mut self, project_dir: Option<P>) {
+        self.project_dir = project_dir.map(|p| p.as_ref().to_path_buf());
+    }
+
+    /// Gets the directory to start the build from, if not the working directory
+    pub fn project_dir(&self) -> Option<&Path> {
+        self.project_dir.as_deref()
+    }
+
+    /// Sets the settings script to use instead of searching for one
+    pub fn set_settings_file<P: AsRef<Path>>(&mut self, settings_file: Option<P>) {
+        self.settings_file = settings_file.map(|p| p.as_ref().to_path_buf());
+    }
+
+    /// Gets the settings script to use instead of searching for one
+    pub fn settings_file(&self) -> Option<&Path> {
+        self.settings_file.as_deref()
+    }
+
+    /// Sets the init scripts to evaluate before the init scripts of the user home
+    pub fn set_init_scripts<I, P>(&mut self, init_scripts: I)
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.init_scripts = init_scripts
+            .into_iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect();
+    }
+
+    /// Gets the init scripts given on the command line
+    pub fn init_scripts(&self) -> &[PathBuf] {
+        &self.init_scripts
+    }
+
+    /// Sets the spider user home to read init scripts and properties from, instead of the
+    /// default one
+    pub fn set_user_home<P: AsRef<Path>>(&mut self, user_home: Option<P>) {
+        self.user_home = user_home.map(|p| p.as_ref().to_path_buf());
+    }
+
+    /// Gets the spider user home to use, if not the default one
+    pub fn user_home(&self) -> Option<&Path> {
+        self.user_home.as_deref()
+    }
+}
@@ -1,8 +1,10 @@
 //! The traits for all value providers.
 
+mod provider_factory;
 mod providers;
 
 use crate::lazy::provider::providers::{AndThenProvider, FlatMapProvider, MapProvider};
+pub use provider_factory::ProviderFactory;
 use std::collections::HashSet;
 
 /// Source of value in a provider
@@ -1,16 +1,55 @@
-use crate::action::Action;
 use crate::beans::{BeanProvider, FromBeanProvider};
 use crate::lazy::provider::Provider;
 use crate::lazy::provider::providers::{JustProvider, ProducerProvider, ValueSourceProvider};
 use crate::lazy::value_source::ValueSource;
+use crate::properties::{BuildProperties, BuildPropertyProvider, PropertyKind};
+use crate::shared::Shared;
 
 /// A provider factory
-#[derive(Clone)]
-pub struct ProviderFactory {}
+#[derive(Debug, Clone)]
+pub struct ProviderFactory {
+    properties: Shared<BuildProperties>,
+    project: String,
+}
 
 impl ProviderFactory {
-    pub(crate) fn new() -> Self {
-        Self {}
+    pub(crate) fn new(properties: Shared<BuildProperties>) -> Self {
+        Self {
+            properties,
+            project: ":".to_string(),
+        }
+    }
+
+    /// Creates a provider factory reading project properties as seen by the project at `path`
+    pub(crate) fn for_project(&self, path: &str) -> Self {
+        Self {
+            properties: self.properties.clone(),
+            project: path.to_string(),
+        }
+    }
+
+    /// Creates a provider of a project property, given with `-P` or from a properties file.
+    ///
+    /// The provider is empty when the property isn't set.
+    pub fn project_property<S: AsRef<str>>(&self, key: S) -> impl Provider<String> + use<S> {
+        BuildPropertyProvider::new(
+            self.properties.clone(),
+            &self.project,
+            PropertyKind::Project,
+            key.as_ref(),
+        )
+    }
+
+    /// Creates a provider of a system property, given with `-D`.
+    ///
+    /// The provider is empty when the property isn't set.
+    pub fn system_property<S: AsRef<str>>(&self, key: S) -> impl Provider<String> + use<S> {
+        BuildPropertyProvider::new(
+            self.properties.clone(),
+            &self.project,
+            PropertyKind::System,
+            key.as_ref(),
+        )
     }
 
     /// Creates a provider that just returns a given value
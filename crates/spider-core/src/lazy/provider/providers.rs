@@ -8,6 +8,7 @@ use std::fmt::Pointer;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::Instrument;
 
 /// A provider of a value `T`
 #[derive(Clone)]
@@ -375,7 +376,9 @@ where
                 else {
                     unreachable!()
                 };
-                let output = vs.get(&props).await;
+                let span =
+                    tracing::trace_span!("resolve provider", source = std::any::type_name::<Vs>());
+                let output = vs.get(&props).instrument(span).await;
                 *inner = ValueSourceProviderInner::Value(output.clone());
                 output
             }
@@ -5,14 +5,22 @@
 
 pub mod action;
 pub mod beans;
+pub mod build_init;
+pub mod dependencies;
 pub mod error;
+pub mod events;
+pub mod execution;
 pub mod finalized;
 pub mod fs;
 pub mod initialization;
 pub mod invocation;
 pub mod lazy;
+pub mod lifecycle;
 pub mod named;
 pub mod project;
+pub mod properties;
+pub mod reporting;
 pub mod shared;
 pub mod table;
 pub mod task;
+pub mod wrapper;
@@ -0,0 +1,276 @@
+//! The lifecycle of a build: the phases it moves through, and the hooks run along the way.
+//!
+//! A build starts in the [initialization](BuildPhase::Initialization) phase, where init scripts
+//! and settings decide which projects take part in it. In the
+//! [configuration](BuildPhase::Configuration) phase every project is configured and the task
+//! graph is computed, and the tasks of the graph run in the [execution](BuildPhase::Execution)
+//! phase. Changes that only make sense in an earlier phase are rejected with
+//! [`ErrorKind::IllegalPhase`].
+
+use crate::action::Action;
+use crate::error::{ErrorKind, Result};
+use crate::execution::graph::TaskGraph;
+use crate::project::Project;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The phases of a build, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BuildPhase {
+    /// Init scripts and settings are evaluated
+    Initialization,
+    /// Projects are configured and the task graph is computed
+    Configuration,
+    /// Tasks are executed
+    Execution,
+    /// The build finished
+    Finished,
+}
+
+impl BuildPhase {
+    const ALL: [BuildPhase; 4] = [
+        BuildPhase::Initialization,
+        BuildPhase::Configuration,
+        BuildPhase::Execution,
+        BuildPhase::Finished,
+    ];
+}
+
+impl Display for BuildPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BuildPhase::Initialization => "initialization",
+            BuildPhase::Configuration => "configuration",
+            BuildPhase::Execution => "execution",
+            BuildPhase::Finished => "finished",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The current phase of a build, shared by everything taking part in it
+#[derive(Debug, Clone)]
+pub struct PhaseTracker {
+    phase: Arc<AtomicU8>,
+}
+
+impl PhaseTracker {
+    /// Creates a tracker of a build that is being initialized
+    pub fn new() -> Self {
+        Self::starting_at(BuildPhase::Initialization)
+    }
+
+    /// Creates a tracker of a build in the given phase
+    pub(crate) fn starting_at(phase: BuildPhase) -> Self {
+        Self {
+            phase: Arc::new(AtomicU8::new(phase as u8)),
+        }
+    }
+
+    /// Gets the current phase
+    pub fn current(&self) -> BuildPhase {
+        BuildPhase::ALL[self.phase.load(Ordering::SeqCst) as usize]
+    }
+
+    /// Moves the build to a phase
+    pub(crate) fn enter(&self, phase: BuildPhase) {
+        tracing::trace!("entering the {phase} phase");
+        self.phase.store(phase as u8, Ordering::SeqCst);
+    }
+
+    /// Checks that the build is in or before the `last` phase `action` is allowed in
+    pub fn check(&self, action: &str, last: BuildPhase) -> std::result::Result<(), ErrorKind> {
+        let phase = self.current();
+        if phase > last {
+            return Err(ErrorKind::IllegalPhase {
+                action: action.to_string(),
+                phase,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for PhaseTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type ProjectHook = Arc<dyn for<'a> Action<&'a mut Project> + Send + Sync>;
+type TaskGraphHook = Arc<dyn for<'a> Action<&'a TaskGraph> + Send + Sync>;
+type BuildFinishedHook = Arc<dyn for<'a> Action<&'a Result<()>> + Send + Sync>;
+
+#[derive(Default)]
+struct Hooks {
+    before_project: Vec<ProjectHook>,
+    after_evaluate: Vec<ProjectHook>,
+    task_graph_ready: Vec<TaskGraphHook>,
+    build_finished: Vec<BuildFinishedHook>,
+}
+
+/// The hooks of a build, run as it moves through its phases. Hooks are run in the order they
+/// were added.
+#[derive(Clone)]
+pub struct BuildHooks {
+    phase: PhaseTracker,
+    hooks: Arc<Mutex<Hooks>>,
+}
+
+impl BuildHooks {
+    /// Creates the hooks of the build whose phase is tracked by `phase`
+    pub fn new(phase: PhaseTracker) -> Self {
+        Self {
+            phase,
+            hooks: Arc::new(Mutex::new(Hooks::default())),
+        }
+    }
+
+    /// Gets the phase of the build
+    pub fn phase(&self) -> &PhaseTracker {
+        &self.phase
+    }
+
+    /// Adds a hook run before each project is configured
+    pub fn before_project<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: for<'a> Action<&'a mut Project> + Send + Sync + 'static,
+    {
+        self.phase
+            .check("add a before_project hook", BuildPhase::Configuration)?;
+        self.hooks
+            .lock()
+            .unwrap()
+            .before_project
+            .push(Arc::new(action));
+        Ok(())
+    }
+
+    /// Adds a hook run after each project is configured
+    pub fn after_evaluate<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: for<'a> Action<&'a mut Project> + Send + Sync + 'static,
+    {
+        self.phase
+            .check("add an after_evaluate hook", BuildPhase::Configuration)?;
+        self.hooks
+            .lock()
+            .unwrap()
+            .after_evaluate
+            .push(Arc::new(action));
+        Ok(())
+    }
+
+    /// Gets the hooks of the task graph
+    pub fn task_graph(&self) -> TaskGraphHooks<'_> {
+        TaskGraphHooks { hooks: self }
+    }
+
+    /// Adds a hook run once the build finished, with its result
+    pub fn build_finished<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: for<'a> Action<&'a Result<()>> + Send + Sync + 'static,
+    {
+        self.phase
+            .check("add a build_finished hook", BuildPhase::Execution)?;
+        self.hooks
+            .lock()
+            .unwrap()
+            .build_finished
+            .push(Arc::new(action));
+        Ok(())
+    }
+
+    /// Runs the before_project hooks against a project
+    pub(crate) fn run_before_project(&self, project: &mut Project) {
+        let hooks = self.hooks.lock().unwrap().before_project.clone();
+        for hook in hooks {
+            hook.execute(project);
+        }
+    }
+
+    /// Runs the after_evaluate hooks against a project
+    pub(crate) fn run_after_evaluate(&self, project: &mut Project) {
+        let hooks = self.hooks.lock().unwrap().after_evaluate.clone();
+        for hook in hooks {
+            hook.execute(project);
+        }
+    }
+
+    /// Runs the hooks waiting for the task graph
+    pub(crate) fn run_task_graph_ready(&self, graph: &TaskGraph) {
+        let hooks = self.hooks.lock().unwrap().task_graph_ready.clone();
+        for hook in hooks {
+            hook.execute(graph);
+        }
+    }
+
+    /// Runs the build_finished hooks with the result of the build
+    pub(crate) fn run_build_finished(&self, result: &Result<()>) {
+        let hooks = self.hooks.lock().unwrap().build_finished.clone();
+        for hook in hooks {
+            hook.execute(result);
+        }
+    }
+}
+
+impl Default for BuildHooks {
+    fn default() -> Self {
+        Self::new(PhaseTracker::new())
+    }
+}
+
+impl Debug for BuildHooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuildHooks")
+            .field("phase", &self.phase.current())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The hooks of the task graph of a build
+pub struct TaskGraphHooks<'a> {
+    hooks: &'a BuildHooks,
+}
+
+impl TaskGraphHooks<'_> {
+    /// Adds a hook run once the task graph is computed, before any task is executed
+    pub fn when_ready<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: for<'a> Action<&'a TaskGraph> + Send + Sync + 'static,
+    {
+        self.hooks.phase.check(
+            "add a task_graph.when_ready hook",
+            BuildPhase::Configuration,
+        )?;
+        self.hooks
+            .hooks
+            .lock()
+            .unwrap()
+            .task_graph_ready
+            .push(Arc::new(action));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_late_hooks() {
+        let hooks = BuildHooks::default();
+        hooks.after_evaluate(|_: &mut Project| {}).unwrap();
+        hooks.phase().enter(BuildPhase::Execution);
+
+        let Err(error) = hooks.before_project(|_: &mut Project| {}) else {
+            panic!("before_project hooks can't be added during execution")
+        };
+        assert_eq!(
+            error.to_string(),
+            "cannot add a before_project hook during the execution phase"
+        );
+        assert!(hooks.build_finished(|_: &Result<()>| {}).is_ok());
+    }
+}
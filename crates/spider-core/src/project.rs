@@ -1,32 +1,642 @@
+//! Projects, the unit of configuration in a spider build.
+//!
+//! The projects of a build form a tree rooted at the root project, with paths such as `:a:b`
+//! naming project `b` inside project `a`. The tree is fixed once it is created from the settings
+//! of the build, so a project's path, directory and relatives are read without awaiting.
+//!
+//! The projects of a build included in a composite build form a tree of their own, whose root
+//! project's path is the name of the included build, such as `:library`.
+
+use crate::action::Action;
 use crate::beans::BeanProvider;
-// use crate::lazy::provider::ProviderFactory;
+use crate::dependencies::{DependencySet, Repository};
+use crate::error::ErrorKind;
+use crate::initialization::settings::ProjectDescriptor;
+use crate::lazy::provider::ProviderFactory;
+use crate::lifecycle::{BuildPhase, PhaseTracker};
+use crate::properties::BuildProperties;
+use crate::shared::{Shared, shared};
+use crate::table::Table;
+use crate::task::container::TaskContainer;
+use crate::task::selection::{NameMatch, match_name};
+use indexmap::{IndexMap, IndexSet};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+
+/// The name of the directory build outputs are written to, relative to the project directory
+pub const BUILD_DIR: &str = "build";
+/// The version of projects that don't set one
+pub const DEFAULT_VERSION: &str = "unspecified";
 
 #[derive(Debug)]
+struct ProjectInner {
+    path: String,
+    name: RwLock<String>,
+    dir: PathBuf,
+    build_dir: RwLock<PathBuf>,
+    group: RwLock<Option<String>>,
+    version: RwLock<String>,
+    artifact_task: RwLock<Option<String>>,
+    dependencies: DependencySet,
+    repositories: RwLock<Vec<Repository>>,
+    plugins: RwLock<IndexSet<String>>,
+    extra: RwLock<Table>,
+    phase: PhaseTracker,
+    parent: Option<Weak<ProjectInner>>,
+    children: RwLock<IndexMap<String, Project>>,
+    tasks: TaskContainer,
+}
+
+/// A project within a spider build
+#[derive(Debug, Clone)]
 pub struct Project {
-    // provider_factory: ProviderFactory,
-    // tasks: Shared<TaskContainer>,
+    inner: Arc<ProjectInner>,
+    provider_factory: ProviderFactory,
+}
+
+impl Project {
+    /// Creates a new root project named `root` in the working directory, without any build
+    /// properties, that is being configured
+    pub fn new() -> Self {
+        Self::root(
+            "root",
+            ".",
+            shared(BuildProperties::new()),
+            PhaseTracker::starting_at(BuildPhase::Configuration),
+        )
+    }
+
+    /// Creates a new root project, reading build properties from `properties`
+    pub(crate) fn root<S: AsRef<str>, P: AsRef<Path>>(
+        name: S,
+        dir: P,
+        properties: Shared<BuildProperties>,
+        phase: PhaseTracker,
+    ) -> Self {
+        Self::create(
+            ":".to_string(),
+            name.as_ref(),
+            dir.as_ref(),
+            None,
+            ProviderFactory::new(properties),
+            phase,
+        )
+    }
+
+    /// Creates the root project of a build included in a composite build, whose path is the name
+    /// of the included build
+    pub(crate) fn included_root<S: AsRef<str>, P: AsRef<Path>>(
+        name: S,
+        dir: P,
+        properties: Shared<BuildProperties>,
+        phase: PhaseTracker,
+    ) -> Self {
+        let name = name.as_ref();
+        Self::create(
+            format!(":{name}"),
+            name,
+            dir.as_ref(),
+            None,
+            ProviderFactory::new(properties),
+            phase,
+        )
+    }
+
+    /// Creates the project tree described by the settings of a build below this root project,
+    /// naming it after the root descriptor. The root project must be described first, and every
+    /// project after its parent.
+    pub(crate) fn build_tree(
+        &self,
+        descriptors: &[ProjectDescriptor],
+    ) -> std::result::Result<(), ErrorKind> {
+        let Some((root, descriptors)) = descriptors.split_first() else {
+            return Ok(());
+        };
+        *self.inner.name.write().unwrap() = root.name().to_string();
+        for descriptor in descriptors {
+            // settings paths are relative to the root, which is not `:` in an included build
+            let parent = match descriptor.path().rsplit_once(':') {
+                Some(("", _)) | None => self.clone(),
+                Some((parent, _)) => self.project(parent.trim_start_matches(':'))?,
+            };
+            parent.add_child(descriptor.name(), descriptor.dir());
+        }
+        Ok(())
+    }
+
+    fn create(
+        path: String,
+        name: &str,
+        dir: &Path,
+        parent: Option<Weak<ProjectInner>>,
+        provider_factory: ProviderFactory,
+        phase: PhaseTracker,
+    ) -> Self {
+        let provider_factory = provider_factory.for_project(&path);
+        Self {
+            inner: Arc::new(ProjectInner {
+                tasks: TaskContainer::new(&path, phase.clone()),
+                dependencies: DependencySet::new(&path, phase.clone()),
+                path,
+                name: RwLock::new(name.to_string()),
+                dir: dir.to_path_buf(),
+                build_dir: RwLock::new(dir.join(BUILD_DIR)),
+                group: RwLock::new(None),
+                version: RwLock::new(DEFAULT_VERSION.to_string()),
+                artifact_task: RwLock::new(None),
+                repositories: RwLock::new(vec![]),
+                plugins: RwLock::new(IndexSet::new()),
+                extra: RwLock::new(Table::new()),
+                phase,
+                parent,
+                children: RwLock::new(IndexMap::new()),
+            }),
+            provider_factory,
+        }
+    }
+
+    /// Adds a child project with the given name and directory
+    pub(crate) fn add_child<S: AsRef<str>, P: AsRef<Path>>(&self, name: S, dir: P) -> Project {
+        let name = name.as_ref();
+        let path = match self.path() {
+            ":" => format!(":{name}"),
+            parent => format!("{parent}:{name}"),
+        };
+        let child = Self::create(
+            path,
+            name,
+            dir.as_ref(),
+            Some(Arc::downgrade(&self.inner)),
+            self.provider_factory.clone(),
+            self.inner.phase.clone(),
+        );
+        self.inner
+            .children
+            .write()
+            .unwrap()
+            .insert(name.to_string(), child.clone());
+        child
+    }
+
+    /// Gets the provider factory of this project
+    pub fn providers(&self) -> ProviderFactory {
+        self.provider_factory.clone()
+    }
+
+    /// Gets the path of this project, such as `:a:b`, or `:` for the root project
+    pub fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    /// Gets the name of this project
+    pub fn name(&self) -> String {
+        self.inner.name.read().unwrap().clone()
+    }
+
+    /// Gets the directory of this project
+    pub fn dir(&self) -> &Path {
+        &self.inner.dir
+    }
+
+    /// Gets the directory build outputs of this project are written to, `build` in the project
+    /// directory unless set
+    pub fn build_dir(&self) -> PathBuf {
+        self.inner.build_dir.read().unwrap().clone()
+    }
+
+    /// Sets the build directory of this project. Relative directories are resolved against the
+    /// project directory.
+    pub fn set_build_dir<P: AsRef<Path>>(&self, dir: P) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the build directory")?;
+        *self.inner.build_dir.write().unwrap() = self.inner.dir.join(dir);
+        Ok(())
+    }
+
+    /// Gets the group of the module this project produces, the name of its root project unless
+    /// set
+    pub fn group(&self) -> String {
+        match &*self.inner.group.read().unwrap() {
+            Some(group) => group.clone(),
+            None => self.root_project().name(),
+        }
+    }
+
+    /// Sets the group of the module this project produces
+    pub fn set_group<S: AsRef<str>>(&self, group: S) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the group")?;
+        *self.inner.group.write().unwrap() = Some(group.as_ref().to_string());
+        Ok(())
+    }
+
+    /// Gets the version of this project, [`DEFAULT_VERSION`] unless set
+    pub fn version(&self) -> String {
+        self.inner.version.read().unwrap().clone()
+    }
+
+    /// Sets the version of this project
+    pub fn set_version<S: AsRef<str>>(&self, version: S) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the version")?;
+        *self.inner.version.write().unwrap() = version.as_ref().to_string();
+        Ok(())
+    }
+
+    /// Gets the name of the task that builds the artifact of this project, if it has one
+    pub fn artifact_task(&self) -> Option<String> {
+        self.inner.artifact_task.read().unwrap().clone()
+    }
+
+    /// Sets the task that builds the artifact of this project. The outputs of the task are used
+    /// by projects that depend on this one.
+    pub fn set_artifact_task<S: AsRef<str>>(&self, name: S) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the artifact task")?;
+        *self.inner.artifact_task.write().unwrap() = Some(name.as_ref().to_string());
+        Ok(())
+    }
+
+    /// Gets the dependencies of this project
+    pub fn dependencies(&self) -> DependencySet {
+        self.inner.dependencies.clone()
+    }
+
+    /// Adds a repository the dependencies of this project are fetched from, unless it was
+    /// already added
+    pub fn add_repository(&self, repository: Repository) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("add a repository")?;
+        let mut repositories = self.inner.repositories.write().unwrap();
+        if !repositories.contains(&repository) {
+            repositories.push(repository);
+        }
+        Ok(())
+    }
+
+    /// Gets the repositories of this project, in the order they were added
+    pub fn repositories(&self) -> Vec<Repository> {
+        self.inner.repositories.read().unwrap().clone()
+    }
+
+    /// Applies a plugin to this project by its id. Applying a plugin again has no effect.
+    pub fn apply_plugin<S: AsRef<str>>(&self, id: S) -> std::result::Result<(), ErrorKind> {
+        let id = id.as_ref();
+        self.check_configurable(&format!("apply plugin {id:?}"))?;
+        self.inner.plugins.write().unwrap().insert(id.to_string());
+        Ok(())
+    }
+
+    /// Checks if a plugin was applied to this project
+    pub fn has_plugin<S: AsRef<str>>(&self, id: S) -> bool {
+        self.inner.plugins.read().unwrap().contains(id.as_ref())
+    }
+
+    /// Gets the ids of the plugins applied to this project, in the order they were applied
+    pub fn plugins(&self) -> Vec<String> {
+        self.inner.plugins.read().unwrap().iter().cloned().collect()
+    }
+
+    /// Gets the extra properties of this project. Project properties, such as those of
+    /// `spider.properties` files, are found through the metatable of the extra properties.
+    pub fn extra(&self) -> RwLockReadGuard<'_, Table> {
+        self.inner.extra.read().unwrap()
+    }
+
+    /// Gets the extra properties of this project to change them, which can't be done once the
+    /// build is executing
+    pub fn extra_mut(&self) -> std::result::Result<RwLockWriteGuard<'_, Table>, ErrorKind> {
+        self.check_configurable("change the extra properties")?;
+        Ok(self.inner.extra.write().unwrap())
+    }
+
+    /// Makes project properties available as extra properties of this project
+    pub(crate) fn set_project_properties(&self, properties: HashMap<String, String>) {
+        let mut table = Table::new();
+        for (key, value) in properties {
+            table.set(&key, value);
+        }
+        self.inner.extra.write().unwrap().set_metatable(table);
+    }
+
+    /// Gets the parent of this project, if it isn't the root project
+    pub fn parent(&self) -> Option<Project> {
+        let parent = self.inner.parent.as_ref()?.upgrade()?;
+        Some(Project {
+            provider_factory: self.provider_factory.for_project(&parent.path),
+            inner: parent,
+        })
+    }
+
+    /// Gets the root project of the build this project belongs to
+    pub fn root_project(&self) -> Project {
+        let mut project = self.clone();
+        while let Some(parent) = project.parent() {
+            project = parent;
+        }
+        project
+    }
+
+    /// Gets the direct children of this project, in the order they were included
+    pub fn children(&self) -> Vec<Project> {
+        self.inner
+            .children
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Gets this project and all projects below it, each project before its children
+    pub fn all_projects(&self) -> Vec<Project> {
+        let mut projects = vec![self.clone()];
+        for child in self.children() {
+            projects.extend(child.all_projects());
+        }
+        projects
+    }
+
+    /// Gets all projects below this project, each project before its children
+    pub fn descendants(&self) -> Vec<Project> {
+        let mut projects = self.all_projects();
+        projects.remove(0);
+        projects
+    }
+
+    /// Finds a project by its path. Paths starting with `:` are absolute, other paths are
+    /// relative to this project. Absolute paths of projects in an included build start with the
+    /// name of the build.
+    pub fn find_project<S: AsRef<str>>(&self, path: S) -> Option<Project> {
+        self.project(path).ok()
+    }
+
+    /// Gets a project by its path, like [`find_project`](Self::find_project), suggesting similar
+    /// projects if it doesn't exist
+    pub fn project<S: AsRef<str>>(&self, path: S) -> std::result::Result<Project, ErrorKind> {
+        let path = path.as_ref();
+        let (mut project, relative) = match path.strip_prefix(':') {
+            Some(relative) => {
+                let root = self.root_project();
+                let relative = match root.path() {
+                    ":" => relative,
+                    root_path => match path.strip_prefix(root_path) {
+                        Some(relative) if relative.is_empty() || relative.starts_with(':') => {
+                            relative
+                        }
+                        _ => {
+                            return Err(ErrorKind::ProjectNotFound {
+                                path: path.to_string(),
+                                suggestions: vec![],
+                            });
+                        }
+                    },
+                };
+                (root, relative)
+            }
+            None => (self.clone(), path),
+        };
+        for name in relative.split(':').filter(|name| !name.is_empty()) {
+            let child = project.inner.children.read().unwrap().get(name).cloned();
+            project = match child {
+                Some(child) => child,
+                None => {
+                    let children = project.inner.children.read().unwrap();
+                    let suggestions = match match_name(name, children.keys().map(String::as_str)) {
+                        NameMatch::NotFound(suggestions) => suggestions,
+                        NameMatch::Found(found) => vec![found],
+                        NameMatch::Ambiguous(candidates) => candidates,
+                    };
+                    return Err(ErrorKind::ProjectNotFound {
+                        path: path.to_string(),
+                        suggestions: suggestions
+                            .iter()
+                            .map(|name| child_path(project.path(), name))
+                            .collect(),
+                    });
+                }
+            };
+        }
+        Ok(project)
+    }
+
+    /// Configures this project and all projects below it
+    pub fn allprojects<A>(&self, action: A)
+    where
+        A: for<'a> Action<&'a mut Project>,
+    {
+        for mut project in self.all_projects() {
+            action.execute(&mut project);
+        }
+    }
+
+    /// Configures all projects below this project
+    pub fn subprojects<A>(&self, action: A)
+    where
+        A: for<'a> Action<&'a mut Project>,
+    {
+        for mut project in self.descendants() {
+            action.execute(&mut project);
+        }
+    }
+
+    /// Gets the phase of the build this project belongs to
+    pub fn phase(&self) -> BuildPhase {
+        self.inner.phase.current()
+    }
+
+    /// Checks that this project can still be configured, which it can't once the build is
+    /// executing
+    fn check_configurable(&self, action: &str) -> std::result::Result<(), ErrorKind> {
+        self.inner.phase.check(
+            &format!("{action} of project {:?}", self.path()),
+            BuildPhase::Configuration,
+        )
+    }
+
+    /// Gets the tasks registered in this project
+    pub async fn tasks(&self) -> TaskContainer {
+        self.inner.tasks.clone()
+    }
+
+    /// Creates a reference to this project that doesn't keep it alive
+    pub(crate) fn downgrade(&self) -> WeakProject {
+        WeakProject {
+            inner: Arc::downgrade(&self.inner),
+            provider_factory: self.provider_factory.clone(),
+        }
+    }
+}
+
+/// A reference to a project that doesn't keep it alive, for the tasks of a build to refer to
+/// projects of the same build
+#[derive(Debug, Clone)]
+pub(crate) struct WeakProject {
+    inner: Weak<ProjectInner>,
+    provider_factory: ProviderFactory,
 }
 
-impl Project {}
+impl WeakProject {
+    /// Gets the project, if it still exists
+    pub(crate) fn upgrade(&self) -> Option<Project> {
+        Some(Project {
+            inner: self.inner.upgrade()?,
+            provider_factory: self.provider_factory.clone(),
+        })
+    }
+}
 
-// impl BeanProvider<ProviderFactory> for Project {
-//     fn get_bean(&self) -> ProviderFactory {
-//         ProviderFactory::new()
-//     }
-// }
+/// Creates the path of the child `name` of the project at `parent`
+fn child_path(parent: &str, name: &str) -> String {
+    match parent {
+        ":" => format!(":{name}"),
+        parent => format!("{parent}:{name}"),
+    }
+}
+
+impl PartialEq for Project {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for Project {}
+
+impl BeanProvider<ProviderFactory> for Project {
+    fn get_bean(&self) -> ProviderFactory {
+        self.providers()
+    }
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    // use crate::beans::BeanProvider;
-    // use crate::lazy::provider::ProviderFactory;
-    // use crate::project::Project;
-    //
-    // #[test]
-    // fn test_register_task() {
-    //     let project = Project {
-    //         provider_factory: ProviderFactory::new(),
-    //     };
-    //     let provider: ProviderFactory = project.get_bean();
-    //     let provider = provider.provider(|| {});
-    // }
+    use crate::beans::BeanProvider;
+    use crate::dependencies::Dependency;
+    use crate::error::ErrorKind;
+    use crate::initialization::settings::Settings;
+    use crate::lazy::provider::{Provider, ProviderFactory};
+    use crate::lifecycle::{BuildPhase, PhaseTracker};
+    use crate::project::Project;
+    use crate::properties::{BuildProperties, PropertySource};
+    use crate::shared::shared;
+    use std::cell::RefCell;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_register_task() {
+        let project = Project::new();
+        let task = project.tasks().await.register("build").await.unwrap();
+        assert_eq!(task.path().await, ":build");
+        assert!(project.tasks().await.find("build").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_project_property_provider() {
+        let properties = shared(BuildProperties::new());
+        let project = Project::root("root", ".", properties.clone(), PhaseTracker::new());
+        let providers: ProviderFactory = project.get_bean();
+        let version = providers.project_property("version");
+        assert_eq!(version.try_get().await, None);
+
+        properties.write().await.set_project_properties(
+            PropertySource::CommandLine,
+            [("version".to_string(), "1.2.3".to_string())],
+        );
+        assert_eq!(version.try_get().await.as_deref(), Some("1.2.3"));
+    }
+
+    #[tokio::test]
+    async fn test_project_tree() {
+        let settings = Settings::new("/repo");
+        settings.include([":a:b", ":c"]).await.unwrap();
+        let root = Project::root(
+            "root",
+            "/repo",
+            shared(BuildProperties::new()),
+            PhaseTracker::new(),
+        );
+        root.build_tree(&settings.projects().await).unwrap();
+
+        assert_eq!(root.name(), "repo");
+        let b = root.project(":a:b").unwrap();
+        assert_eq!((b.name().as_str(), b.dir()), ("b", Path::new("/repo/a/b")));
+        assert_eq!(b.build_dir(), Path::new("/repo/a/b/build"));
+        assert_eq!(b.parent().unwrap().path(), ":a");
+        assert_eq!(b.root_project(), root);
+        assert_eq!(root.project(":a").unwrap().project("b").unwrap(), b);
+        let paths = |projects: Vec<Project>| {
+            projects
+                .iter()
+                .map(|project| project.path().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(root.all_projects()), [":", ":a", ":a:b", ":c"]);
+        assert_eq!(paths(root.children()), [":a", ":c"]);
+
+        let Err(ErrorKind::ProjectNotFound { suggestions, .. }) = root.project(":a:bb") else {
+            panic!("expected project not found")
+        };
+        assert_eq!(suggestions, [":a:b"]);
+    }
+
+    #[tokio::test]
+    async fn test_included_build_paths() {
+        let root = Project::included_root(
+            "library",
+            "/library",
+            shared(BuildProperties::new()),
+            PhaseTracker::new(),
+        );
+        let util = root.add_child("util", "/library/util");
+        assert_eq!(util.path(), ":library:util");
+        assert_eq!(
+            util.tasks()
+                .await
+                .register("jar")
+                .await
+                .unwrap()
+                .path()
+                .await,
+            ":library:util:jar"
+        );
+        assert_eq!(root.project(":library:util").unwrap(), util);
+        assert_eq!(util.project(":library").unwrap(), root);
+        assert!(root.find_project(":util").is_none());
+        assert_eq!(util.group(), "library");
+    }
+
+    #[tokio::test]
+    async fn test_configure_subprojects() {
+        let root = Project::new();
+        root.add_child("a", "a").add_child("b", "a/b");
+        root.subprojects(|project: &mut Project| project.set_build_dir("out").unwrap());
+        assert_eq!(root.build_dir(), Path::new("./build"));
+        assert_eq!(
+            root.project(":a:b").unwrap().build_dir(),
+            Path::new("a/b/out")
+        );
+
+        let names = RefCell::new(vec![]);
+        root.allprojects(|project: &mut Project| {
+            names.borrow_mut().push(project.name());
+        });
+        assert_eq!(names.into_inner(), ["root", "a", "b"]);
+
+        root.inner.phase.enter(BuildPhase::Execution);
+        assert!(matches!(
+            root.set_build_dir("late"),
+            Err(ErrorKind::IllegalPhase { .. })
+        ));
+        assert!(matches!(
+            root.extra_mut(),
+            Err(ErrorKind::IllegalPhase { .. })
+        ));
+        assert!(matches!(
+            root.dependencies().add(Dependency::project(":a")).await,
+            Err(ErrorKind::IllegalPhase { .. })
+        ));
+        assert!(root.dependencies().all().await.is_empty());
+    }
 }
@@ -0,0 +1,266 @@
+//! Project (`-P`) and system (`-D`) properties of a build.
+//!
+//! Project properties are also read from `spider.properties` files in the spider user home, the
+//! root of the build and the directories of its projects. Properties given on the command line
+//! take precedence over those of the user home, which take precedence over those of projects.
+//! Projects of an included build see the properties files of that build, not those of the
+//! build including it.
+
+use crate::lazy::provider::{Provider, ProviderSource};
+use crate::shared::Shared;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use tracing::Instrument;
+
+/// The file name of properties files
+pub const PROPERTIES_FILE: &str = "spider.properties";
+
+/// Parses the `key=value` lines of a properties file. Keys may also be separated from values by
+/// `:`, and lines starting with `#` or `!` are comments.
+pub fn parse_properties(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+        .map(|line| match line.split_once(['=', ':']) {
+            Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Reads the properties file in `dir`, if there is one
+pub fn read_properties_file(dir: &Path) -> io::Result<HashMap<String, String>> {
+    match std::fs::read_to_string(dir.join(PROPERTIES_FILE)) {
+        Ok(text) => Ok(parse_properties(&text)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Where a project property was defined, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PropertySource {
+    /// Given with `-P` on the command line
+    CommandLine,
+    /// Read from the user's home directory
+    UserHome,
+    /// Read from the root directory of the build
+    Project,
+}
+
+/// The kind of a build property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropertyKind {
+    /// A project property, given with `-P`
+    Project,
+    /// A system property, given with `-D`
+    System,
+}
+
+/// All project and system properties known to a build
+#[derive(Debug, Default)]
+pub struct BuildProperties {
+    project: BTreeMap<PropertySource, HashMap<String, String>>,
+    project_dirs: HashMap<String, HashMap<String, String>>,
+    included_roots: HashSet<String>,
+    system: HashMap<String, String>,
+}
+
+impl BuildProperties {
+    /// Creates an empty set of properties
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the project properties defined by a source, replacing any it defined before
+    pub fn set_project_properties<I>(&mut self, source: PropertySource, properties: I)
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.project
+            .insert(source, properties.into_iter().collect());
+    }
+
+    /// Sets the project properties read from the directory of the project at `path`, replacing
+    /// any read before. The properties of the root directory of the build are set with
+    /// [`PropertySource::Project`] instead.
+    pub fn set_project_dir_properties<I>(&mut self, path: &str, properties: I)
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.project_dirs
+            .insert(path.to_string(), properties.into_iter().collect());
+    }
+
+    /// Marks the project at `path` as the root project of an included build, whose projects
+    /// don't see the properties of the directories above it
+    pub fn set_included_build_root(&mut self, path: &str) {
+        self.included_roots.insert(path.to_string());
+    }
+
+    /// Gets the sets of project properties seen by the project at `path`, highest precedence
+    /// first: the command line, the user home, the directories of the project and its parents,
+    /// and the root directory of the build, unless the project belongs to an included build
+    fn project_layers<'a>(&'a self, path: &str) -> Vec<&'a HashMap<String, String>> {
+        let mut layers = self
+            .project
+            .range(..PropertySource::Project)
+            .map(|(_, properties)| properties)
+            .collect::<Vec<_>>();
+        for path in project_and_parents(path) {
+            layers.extend(self.project_dirs.get(path));
+            if self.included_roots.contains(path) {
+                return layers;
+            }
+        }
+        layers.extend(self.project.get(&PropertySource::Project));
+        layers
+    }
+
+    /// Gets a project property as seen by the project at `path`
+    pub fn project_property_in(&self, path: &str, key: &str) -> Option<&str> {
+        self.project_layers(path)
+            .into_iter()
+            .find_map(|properties| properties.get(key))
+            .map(String::as_str)
+    }
+
+    /// Gets every project property seen by the project at `path`
+    pub fn project_properties_in(&self, path: &str) -> HashMap<String, String> {
+        let mut merged = HashMap::new();
+        for properties in self.project_layers(path).into_iter().rev() {
+            merged.extend(properties.clone());
+        }
+        merged
+    }
+
+    /// Sets the system properties, replacing any defined before
+    pub fn set_system_properties<I>(&mut self, properties: I)
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.system = properties.into_iter().collect();
+    }
+
+    /// Gets a project property from the source with the highest precedence that defines it,
+    /// as seen by the root project
+    pub fn project_property(&self, key: &str) -> Option<&str> {
+        self.project_property_in(":", key)
+    }
+
+    /// Gets a system property
+    pub fn system_property(&self, key: &str) -> Option<&str> {
+        self.system.get(key).map(String::as_str)
+    }
+
+    /// Gets a property of the given kind, as seen by the project at `path`
+    pub fn get(&self, kind: PropertyKind, path: &str, key: &str) -> Option<&str> {
+        match kind {
+            PropertyKind::Project => self.project_property_in(path, key),
+            PropertyKind::System => self.system_property(key),
+        }
+    }
+}
+
+/// Gets a project path followed by the paths of its parents, without the root project
+fn project_and_parents(path: &str) -> Vec<&str> {
+    let mut paths = vec![];
+    let mut path = path;
+    while !path.is_empty() && path != ":" {
+        paths.push(path);
+        path = match path.rsplit_once(':') {
+            Some((parent, _)) => parent,
+            None => "",
+        };
+    }
+    paths
+}
+
+/// A [`Provider`] of a build property, which is empty when the property isn't set
+#[derive(Clone)]
+pub(crate) struct BuildPropertyProvider {
+    properties: Shared<BuildProperties>,
+    project: String,
+    kind: PropertyKind,
+    key: String,
+}
+
+impl BuildPropertyProvider {
+    pub(crate) fn new(
+        properties: Shared<BuildProperties>,
+        project: &str,
+        kind: PropertyKind,
+        key: &str,
+    ) -> Self {
+        Self {
+            properties,
+            project: project.to_string(),
+            kind,
+            key: key.to_string(),
+        }
+    }
+}
+
+impl Provider<String> for BuildPropertyProvider {
+    async fn try_get(&self) -> Option<String> {
+        let span = tracing::trace_span!("resolve provider", key = %self.key);
+        async {
+            let properties = self.properties.read().await;
+            properties
+                .get(self.kind, &self.project, &self.key)
+                .map(str::to_string)
+        }
+        .instrument(span)
+        .await
+    }
+
+    fn sources(&self) -> HashSet<ProviderSource> {
+        HashSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_precedence() {
+        let mut properties = BuildProperties::new();
+        properties.set_project_properties(
+            PropertySource::Project,
+            [("version".to_string(), "1.0".to_string())],
+        );
+        properties.set_project_properties(
+            PropertySource::CommandLine,
+            [("version".to_string(), "2.0".to_string())],
+        );
+        assert_eq!(properties.project_property("version"), Some("2.0"));
+        assert_eq!(properties.project_property("missing"), None);
+    }
+
+    #[test]
+    fn test_project_dir_precedence() {
+        let mut properties = BuildProperties::new();
+        properties.set_project_properties(
+            PropertySource::Project,
+            parse_properties("# root\nversion=1.0\nflags: -Xmx1g\n"),
+        );
+        properties.set_project_dir_properties(":a", parse_properties("version = 1.1\n"));
+        properties
+            .set_project_properties(PropertySource::UserHome, parse_properties("flags=-Xmx4g\n"));
+
+        assert_eq!(properties.project_property("version"), Some("1.0"));
+        assert_eq!(
+            properties.project_property_in(":a:b", "version"),
+            Some("1.1")
+        );
+        assert_eq!(
+            properties.project_property_in(":a", "flags"),
+            Some("-Xmx4g")
+        );
+        let merged = properties.project_properties_in(":a");
+        assert_eq!(merged["version"], "1.1");
+        assert_eq!(merged["flags"], "-Xmx4g");
+    }
+}
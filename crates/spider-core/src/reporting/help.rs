@@ -0,0 +1,202 @@
+//! The `help` task, which prints usage information or details about a single task
+
+use crate::error::{Error, Result};
+use crate::project::{Project, WeakProject};
+use crate::reporting::HELP_GROUP;
+use crate::task::options::TaskOption;
+use crate::task::selection::select_task_path;
+use crate::task::{Task, TaskError, from_fn};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The name of the help task
+pub const HELP_TASK: &str = "help";
+
+/// Registers the `help` task in the given project. Tasks are looked up in every project of the
+/// trees of `root_projects`.
+pub async fn register(project: &Project, root_projects: &[Project]) -> Result<Task> {
+    let root_projects = root_projects
+        .iter()
+        .map(Project::downgrade)
+        .collect::<Vec<_>>();
+    let task = project.tasks().await.register(HELP_TASK).await?;
+    task.set_type_name("Help").await?;
+    task.set_group(HELP_GROUP).await?;
+    task.set_description("Displays a help message.").await?;
+    task.add_option(TaskOption::with_value(
+        "task",
+        "The task to show detailed information for.",
+    ))
+    .await?;
+    task.do_last(from_fn(move |task: Task, _| {
+        let root_projects = root_projects.clone();
+        async move {
+            let requested = task
+                .option("task")
+                .await
+                .and_then(|option| option.value().map(str::to_string));
+            match requested {
+                Some(requested) => {
+                    let root_projects = root_projects
+                        .iter()
+                        .filter_map(WeakProject::upgrade)
+                        .collect::<Vec<_>>();
+                    let details = TaskDetails::find(&root_projects, &requested)
+                        .await
+                        .map_err(TaskError::Fail)?;
+                    print!("{}", details.render());
+                }
+                None => print!("{}", welcome()),
+            }
+            Ok(())
+        }
+    }))
+    .await?;
+    Ok(task)
+}
+
+/// The message printed when `help` is run without a task
+fn welcome() -> String {
+    "\nWelcome to Spider.\n\n\
+     To see a list of available tasks, run spider tasks\n\n\
+     To see more detail about a task, run spider help --task <task>\n\n\
+     To see a list of command-line options, run spider --help\n"
+        .to_string()
+}
+
+/// Everything known about a single task, ready to be rendered
+#[derive(Debug)]
+pub struct TaskDetails {
+    path: String,
+    project: String,
+    type_name: String,
+    group: Option<String>,
+    description: Option<String>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    dependencies: Vec<String>,
+    options: Vec<TaskOption>,
+}
+
+impl TaskDetails {
+    /// Finds a task in the projects of the trees of `root_projects`, allowing abbreviated task
+    /// names
+    pub async fn find(
+        root_projects: &[Project],
+        requested: &str,
+    ) -> std::result::Result<Self, Error> {
+        let mut tasks = HashMap::new();
+        for project in root_projects.iter().flat_map(Project::all_projects) {
+            for task in project.tasks().await.all().await {
+                tasks.insert(task.path().await, task);
+            }
+        }
+        let path = select_task_path(requested, tasks.keys().map(String::as_str))?;
+        Ok(Self::collect(&tasks[&path]).await)
+    }
+
+    /// Collects the details of a task
+    pub async fn collect(task: &Task) -> Self {
+        let display = |paths: Vec<std::path::PathBuf>| {
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect()
+        };
+        Self {
+            path: task.path().await,
+            project: task.project_path().await,
+            type_name: task.type_name().await,
+            group: task.group().await,
+            description: task.description().await,
+            inputs: display(task.inputs().await),
+            outputs: display(task.outputs().await),
+            dependencies: task.dependencies().await,
+            options: task.options().await,
+        }
+    }
+
+    /// Renders these details as sections of a heading followed by indented values.
+    ///
+    /// Empty sections contain the single value `(none)`.
+    pub fn render(&self) -> String {
+        let mut output = format!("Detailed task information for {}\n", self.path);
+        let width = self
+            .options
+            .iter()
+            .map(|option| option.name().len() + 2)
+            .max()
+            .unwrap_or_default();
+        let options = self
+            .options
+            .iter()
+            .map(|option| {
+                let name = format!("--{}", option.name());
+                format!("{name:width$}     {}", option.description())
+            })
+            .collect::<Vec<_>>();
+        let sections: [(&str, Vec<String>); 9] = [
+            ("Path", vec![self.path.clone()]),
+            ("Project", vec![self.project.clone()]),
+            ("Type", vec![self.type_name.clone()]),
+            ("Group", self.group.iter().cloned().collect()),
+            ("Description", self.description.iter().cloned().collect()),
+            ("Inputs", self.inputs.clone()),
+            ("Outputs", self.outputs.clone()),
+            ("Dependencies", self.dependencies.clone()),
+            ("Options", options),
+        ];
+        for (heading, values) in sections {
+            writeln!(output, "\n{heading}").unwrap();
+            if values.is_empty() {
+                writeln!(output, "     (none)").unwrap();
+            }
+            for value in values {
+                writeln!(output, "     {value}").unwrap();
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporting::register_help_tasks;
+
+    #[tokio::test]
+    async fn test_help_task_details() {
+        let project = Project::new();
+        register_help_tasks(&project, std::slice::from_ref(&project))
+            .await
+            .unwrap();
+        let details = TaskDetails::find(std::slice::from_ref(&project), "tas")
+            .await
+            .unwrap();
+        let rendered = details.render();
+        assert!(rendered.starts_with("Detailed task information for :tasks\n"));
+        assert!(rendered.contains("\nProject\n     :\n"));
+        assert!(rendered.contains("\nGroup\n     help\n"));
+        assert!(rendered.contains("\nDependencies\n     (none)\n"));
+        assert!(
+            rendered.contains("\nOptions\n     --all     Show tasks that are not in a group.\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subproject_task_details() {
+        let root = Project::new();
+        let app = root.add_child("app", "app");
+        for project in [&root, &app] {
+            register_help_tasks(project, std::slice::from_ref(&root))
+                .await
+                .unwrap();
+        }
+        let details = TaskDetails::find(std::slice::from_ref(&root), ":app:tasks")
+            .await
+            .unwrap();
+        let rendered = details.render();
+        assert!(rendered.starts_with("Detailed task information for :app:tasks\n"));
+        assert!(rendered.contains("\nProject\n     :app\n"));
+    }
+}
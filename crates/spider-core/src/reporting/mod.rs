@@ -0,0 +1,18 @@
+//! Built-in tasks that report on the build
+
+pub mod help;
+pub mod tasks;
+
+use crate::error::Result;
+use crate::project::Project;
+
+/// The group of the built-in reporting tasks
+pub const HELP_GROUP: &str = "help";
+
+/// Registers the built-in reporting tasks in a project of the build made of the trees of
+/// `root_projects`
+pub async fn register_help_tasks(project: &Project, root_projects: &[Project]) -> Result<()> {
+    help::register(project, root_projects).await?;
+    tasks::register(project).await?;
+    Ok(())
+}
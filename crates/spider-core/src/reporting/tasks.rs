@@ -0,0 +1,166 @@
+//! The `tasks` report, which lists the tasks of a project by group
+
+use crate::error::Result;
+use crate::project::Project;
+use crate::reporting::HELP_GROUP;
+use crate::task::options::TaskOption;
+use crate::task::{Task, from_fn};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// The name of the task that lists tasks
+pub const TASKS_TASK: &str = "tasks";
+
+/// Registers the `tasks` task in the given project
+pub async fn register(project: &Project) -> Result<Task> {
+    let task = project.tasks().await.register(TASKS_TASK).await?;
+    task.set_type_name("TasksReport").await?;
+    task.set_group(HELP_GROUP).await?;
+    task.set_description(format!(
+        "Displays the tasks runnable from project '{}'.",
+        project.path()
+    ))
+    .await?;
+    task.add_option(TaskOption::flag(
+        "all",
+        "Show tasks that are not in a group.",
+    ))
+    .await?;
+    task.do_last(from_fn(|task: Task, project: Project| async move {
+        let all = task
+            .option("all")
+            .await
+            .is_some_and(|option| option.value().is_some());
+        let report = TasksReport::collect(&project).await;
+        print!("{}", report.render(all));
+        Ok(())
+    }))
+    .await?;
+    Ok(task)
+}
+
+/// A single task in a [`TasksReport`]
+#[derive(Debug, Clone)]
+struct TaskEntry {
+    name: String,
+    group: Option<String>,
+    description: Option<String>,
+}
+
+/// The tasks of a project, ready to be rendered
+#[derive(Debug)]
+pub struct TasksReport {
+    project_path: String,
+    entries: Vec<TaskEntry>,
+}
+
+impl TasksReport {
+    /// Collects the tasks registered in a project
+    pub async fn collect(project: &Project) -> Self {
+        let mut entries = vec![];
+        for task in project.tasks().await.all().await {
+            entries.push(TaskEntry {
+                name: task.name().await,
+                group: task.group().await,
+                description: task.description().await,
+            });
+        }
+        Self {
+            project_path: project.path().to_string(),
+            entries,
+        }
+    }
+
+    /// Renders this report, including tasks without a group if `all` is set
+    pub fn render(&self, all: bool) -> String {
+        let mut groups: BTreeMap<Option<&str>, Vec<&TaskEntry>> = BTreeMap::new();
+        for entry in &self.entries {
+            if entry.group.is_some() || all {
+                groups
+                    .entry(entry.group.as_deref())
+                    .or_default()
+                    .push(entry);
+            }
+        }
+
+        let mut output = String::new();
+        let title = format!("Tasks runnable from project '{}'", self.project_path);
+        let rule = "-".repeat(60);
+        writeln!(output, "{rule}\n{title}\n{rule}").unwrap();
+        if groups.is_empty() {
+            writeln!(output, "\nNo tasks").unwrap();
+        }
+        // ungrouped tasks are listed last
+        let ungrouped = groups.remove(&None);
+        let groups = groups
+            .into_iter()
+            .map(|(group, entries)| (group.map(group_title), entries))
+            .chain(ungrouped.map(|entries| (Some("Other tasks".to_string()), entries)));
+        for (title, mut entries) in groups {
+            let title = title.unwrap_or_default();
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            writeln!(output, "\n{title}\n{}", "-".repeat(title.len())).unwrap();
+            for entry in entries {
+                match &entry.description {
+                    Some(description) => writeln!(output, "{} - {description}", entry.name),
+                    None => writeln!(output, "{}", entry.name),
+                }
+                .unwrap();
+            }
+        }
+        if !all {
+            writeln!(
+                output,
+                "\nTo see all tasks and more detail, run spider tasks --all"
+            )
+            .unwrap();
+        }
+        output
+    }
+}
+
+/// Creates the heading of a task group, such as `Build tasks` for `build`
+fn group_title(group: &str) -> String {
+    let mut chars = group.chars();
+    match chars.next() {
+        Some(first) => format!("{}{} tasks", first.to_uppercase(), chars.as_str()),
+        None => "Tasks".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> TasksReport {
+        let entry = |name: &str, group: Option<&str>, description: Option<&str>| TaskEntry {
+            name: name.to_string(),
+            group: group.map(str::to_string),
+            description: description.map(str::to_string),
+        };
+        TasksReport {
+            project_path: ":".to_string(),
+            entries: vec![
+                entry("test", Some("verification"), Some("Runs the tests.")),
+                entry("build", Some("build"), Some("Assembles the project.")),
+                entry("generate", None, None),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render_grouped() {
+        let rendered = report().render(false);
+        let build = rendered.find("Build tasks").unwrap();
+        let verification = rendered.find("Verification tasks").unwrap();
+        assert!(build < verification);
+        assert!(rendered.contains("test - Runs the tests.\n"));
+        assert!(!rendered.contains("generate"));
+    }
+
+    #[test]
+    fn test_render_all() {
+        let rendered = report().render(true);
+        assert!(rendered.contains("Other tasks\n-----------\ngenerate\n"));
+    }
+}
@@ -0,0 +1,93 @@
+//! The [`TaskContainer`], which holds all tasks registered in a project
+
+use crate::error::{Error, ErrorKind};
+use crate::lifecycle::{BuildPhase, PhaseTracker};
+use crate::shared::{Shared, shared};
+use crate::task::Task;
+use indexmap::IndexMap;
+
+#[derive(Debug)]
+struct TaskContainerInner {
+    project_path: String,
+    phase: PhaseTracker,
+    tasks: IndexMap<String, Task>,
+}
+
+/// Contains the tasks of a project, in registration order
+#[derive(Debug, Clone)]
+pub struct TaskContainer {
+    inner: Shared<TaskContainerInner>,
+}
+
+impl TaskContainer {
+    /// Creates a new, empty task container for the project at the given path
+    pub(crate) fn new(project_path: &str, phase: PhaseTracker) -> Self {
+        Self {
+            inner: shared(TaskContainerInner {
+                project_path: project_path.to_string(),
+                phase,
+                tasks: IndexMap::new(),
+            }),
+        }
+    }
+
+    /// Registers a new task with the given name. Tasks can't be registered once the build is
+    /// executing.
+    pub async fn register<S: AsRef<str>>(&self, name: S) -> Result<Task, Error> {
+        let name = name.as_ref();
+        let mut inner = self.inner.write().await;
+        let path = task_path(&inner.project_path, name);
+        inner.phase.check(
+            &format!("register task {path:?}"),
+            BuildPhase::Configuration,
+        )?;
+        if inner.tasks.contains_key(name) {
+            return Err(ErrorKind::TaskAlreadyExists { path }.into());
+        }
+        let task = Task::with_phase(path, inner.phase.clone());
+        inner.tasks.insert(name.to_string(), task.clone());
+        Ok(task)
+    }
+
+    /// Finds a task by its name
+    pub async fn find<S: AsRef<str>>(&self, name: S) -> Option<Task> {
+        self.inner.read().await.tasks.get(name.as_ref()).cloned()
+    }
+
+    /// Gets the names of all registered tasks
+    pub async fn names(&self) -> Vec<String> {
+        self.inner.read().await.tasks.keys().cloned().collect()
+    }
+
+    /// Gets all registered tasks
+    pub async fn all(&self) -> Vec<Task> {
+        self.inner.read().await.tasks.values().cloned().collect()
+    }
+}
+
+/// Creates the path of a task within the project at `project_path`
+fn task_path(project_path: &str, name: &str) -> String {
+    if project_path == ":" {
+        format!(":{name}")
+    } else {
+        format!("{project_path}:{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_duplicate() {
+        let phase = PhaseTracker::new();
+        let container = TaskContainer::new(":sub", phase.clone());
+        let task = container.register("test").await.unwrap();
+        assert_eq!(task.path().await, ":sub:test");
+        assert!(container.register("test").await.is_err());
+
+        phase.enter(BuildPhase::Execution);
+        let error = container.register("late").await.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::IllegalPhase { .. }));
+    }
+}
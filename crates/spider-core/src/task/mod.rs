@@ -1,42 +1,339 @@
 //! Represents an atomic piece of work in a project
 
-use std::cell::Ref;
+pub mod container;
+pub mod options;
+pub mod selection;
+
 use crate::action::Action;
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
 use crate::finalized::Finalize;
+use crate::lifecycle::{BuildPhase, PhaseTracker};
 use crate::project::Project;
-use crate::shared::{shared, Shared};
+use crate::shared::{Shared, shared};
+use crate::task::options::TaskOption;
+use indexmap::IndexMap;
+use std::cell::Ref;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 use sync_wrapper::SyncWrapper;
 use tokio::sync::Mutex;
+use tracing::Instrument;
 
 pub type Result = std::result::Result<(), TaskError>;
 
 #[derive(Debug)]
 struct TaskInner {
     path: String,
+    type_name: String,
+    group: Option<String>,
+    description: Option<String>,
+    options: IndexMap<String, TaskOption>,
+    dependencies: Vec<String>,
+    inputs: Vec<PathBuf>,
+    outputs: Vec<PathBuf>,
+    consumes_dependencies: bool,
+    actions: Arc<Mutex<Vec<BoxTaskAction>>>,
 }
 
+/// The type name of tasks that don't set one
+pub const DEFAULT_TASK_TYPE: &str = "Task";
+
 /// A task
 #[derive(Debug, Clone)]
 pub struct Task {
     inner: Shared<Finalize<TaskInner>>,
+    phase: PhaseTracker,
 }
 
 impl Task {
-    /// Creates a new task
+    /// Creates a new task, of a build that is being configured
     pub fn new<S: AsRef<str>>(path: S) -> Self {
+        Self::with_phase(path, PhaseTracker::starting_at(BuildPhase::Configuration))
+    }
+
+    /// Creates a new task of the build whose phase `phase` tracks
+    pub(crate) fn with_phase<S: AsRef<str>>(path: S, phase: PhaseTracker) -> Self {
         Self {
             inner: shared(Finalize::new(TaskInner {
                 path: path.as_ref().to_string(),
+                type_name: DEFAULT_TASK_TYPE.to_string(),
+                group: None,
+                description: None,
+                options: IndexMap::new(),
+                dependencies: vec![],
+                inputs: vec![],
+                outputs: vec![],
+                consumes_dependencies: false,
+                actions: Arc::default(),
             })),
+            phase,
         }
     }
 
     pub async fn path(&self) -> String {
         self.inner.read().await.path.clone()
     }
+
+    /// Gets the name of this task, the last segment of its path
+    pub async fn name(&self) -> String {
+        let inner = self.inner.read().await;
+        inner
+            .path
+            .rsplit(':')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Gets the path of the project this task belongs to
+    pub async fn project_path(&self) -> String {
+        let inner = self.inner.read().await;
+        match inner.path.rsplit_once(':') {
+            Some(("", _)) | None => ":".to_string(),
+            Some((project, _)) => project.to_string(),
+        }
+    }
+
+    /// Gets the name of this task's type
+    pub async fn type_name(&self) -> String {
+        self.inner.read().await.type_name.clone()
+    }
+
+    /// Sets the name of this task's type
+    pub async fn set_type_name<S: AsRef<str>>(
+        &self,
+        type_name: S,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the type").await?;
+        self.inner.write().await.type_name = type_name.as_ref().to_string();
+        Ok(())
+    }
+
+    /// Adds a dependency on another task.
+    ///
+    /// Paths without a leading `:` are relative to this task's project.
+    pub async fn depends_on<S: AsRef<str>>(&self, path: S) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("add a dependency").await?;
+        let path = path.as_ref();
+        let path = if path.starts_with(':') {
+            path.to_string()
+        } else {
+            match self.project_path().await.as_str() {
+                ":" => format!(":{path}"),
+                project => format!("{project}:{path}"),
+            }
+        };
+        self.inner.write().await.dependencies.push(path);
+        Ok(())
+    }
+
+    /// Gets the paths of the tasks this task depends on
+    pub async fn dependencies(&self) -> Vec<String> {
+        self.inner.read().await.dependencies.clone()
+    }
+
+    /// Declares a file or directory this task reads
+    pub async fn add_input<P: AsRef<Path>>(&self, path: P) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("add an input").await?;
+        self.inner
+            .write()
+            .await
+            .inputs
+            .push(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    /// Gets the declared inputs of this task
+    pub async fn inputs(&self) -> Vec<PathBuf> {
+        self.inner.read().await.inputs.clone()
+    }
+
+    /// Declares a file or directory this task produces
+    pub async fn add_output<P: AsRef<Path>>(&self, path: P) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("add an output").await?;
+        self.inner
+            .write()
+            .await
+            .outputs
+            .push(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    /// Gets the declared outputs of this task
+    pub async fn outputs(&self) -> Vec<PathBuf> {
+        self.inner.read().await.outputs.clone()
+    }
+
+    /// Sets whether this task uses the dependencies of its project. Such a task depends on the
+    /// tasks building the artifacts of the projects its project depends on.
+    pub async fn set_consumes_dependencies(
+        &self,
+        consumes: bool,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set whether it consumes dependencies")
+            .await?;
+        self.inner.write().await.consumes_dependencies = consumes;
+        Ok(())
+    }
+
+    /// Checks if this task uses the dependencies of its project
+    pub async fn consumes_dependencies(&self) -> bool {
+        self.inner.read().await.consumes_dependencies
+    }
+
+    /// Gets the group this task belongs to, used when reporting tasks
+    pub async fn group(&self) -> Option<String> {
+        self.inner.read().await.group.clone()
+    }
+
+    /// Sets the group of this task
+    pub async fn set_group<S: AsRef<str>>(&self, group: S) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the group").await?;
+        self.inner.write().await.group = Some(group.as_ref().to_string());
+        Ok(())
+    }
+
+    /// Gets the description of this task
+    pub async fn description(&self) -> Option<String> {
+        self.inner.read().await.description.clone()
+    }
+
+    /// Sets the description of this task
+    pub async fn set_description<S: AsRef<str>>(
+        &self,
+        description: S,
+    ) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable("set the description").await?;
+        self.inner.write().await.description = Some(description.as_ref().to_string());
+        Ok(())
+    }
+
+    /// Declares a command line option for this task
+    pub async fn add_option(&self, option: TaskOption) -> std::result::Result<(), ErrorKind> {
+        self.check_configurable(&format!("add option {:?}", option.name()))
+            .await?;
+        self.inner
+            .write()
+            .await
+            .options
+            .insert(option.name().to_string(), option);
+        Ok(())
+    }
+
+    /// Gets the command line options declared by this task
+    pub async fn options(&self) -> Vec<TaskOption> {
+        self.inner.read().await.options.values().cloned().collect()
+    }
+
+    /// Gets a declared option by its name
+    pub async fn option<S: AsRef<str>>(&self, name: S) -> Option<TaskOption> {
+        self.inner.read().await.options.get(name.as_ref()).cloned()
+    }
+
+    /// Sets the value of a declared option from the command line
+    pub async fn set_option<S: AsRef<str>>(
+        &self,
+        name: S,
+        value: Option<String>,
+    ) -> std::result::Result<(), Error> {
+        let name = name.as_ref();
+        self.check_configurable(&format!("set option {name:?}"))
+            .await?;
+        let mut inner = self.inner.write().await;
+        let path = inner.path.clone();
+        let Some(option) = inner.options.get_mut(name) else {
+            return Err(ErrorKind::UnknownTaskOption {
+                path,
+                option: name.to_string(),
+            }
+            .into());
+        };
+        option
+            .set_value(value)
+            .map_err(|message| ErrorKind::InvalidTaskOption {
+                path,
+                option: name.to_string(),
+                message,
+            })?;
+        Ok(())
+    }
+
+    /// Adds an action to the start of this task's action list
+    pub async fn do_first<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: TaskAction + Send + 'static,
+    {
+        self.check_configurable("add an action").await?;
+        let actions = self.inner.write().await.actions.clone();
+        actions.lock().await.insert(0, BoxTaskAction::new(action));
+        Ok(())
+    }
+
+    /// Adds an action to the end of this task's action list
+    pub async fn do_last<A>(&self, action: A) -> std::result::Result<(), ErrorKind>
+    where
+        A: TaskAction + Send + 'static,
+    {
+        self.check_configurable("add an action").await?;
+        let actions = self.inner.write().await.actions.clone();
+        actions.lock().await.push(BoxTaskAction::new(action));
+        Ok(())
+    }
+
+    /// Prepares this task for another run of its build, clearing the options the last run set
+    pub(crate) async fn reset(&self) {
+        let mut inner = self.inner.write().await;
+        inner.unfinalize();
+        for option in inner.options.values_mut() {
+            option.clear_value();
+        }
+    }
+
+    /// Checks that this task can still be configured, which it can't once the build is executing
+    async fn check_configurable(&self, action: &str) -> std::result::Result<(), ErrorKind> {
+        let path = self.path().await;
+        self.phase.check(
+            &format!("{action} of task {path:?}"),
+            BuildPhase::Configuration,
+        )
+    }
+
+    /// Executes this task's actions in order, finalizing the task beforehand.
+    ///
+    /// The actions are kept, so a build that stays loaded can execute the task again.
+    pub async fn execute(&self, project: Project) -> Result {
+        let (path, actions) = {
+            let mut inner = self.inner.write().await;
+            inner.finalize();
+            (inner.path.clone(), inner.actions.clone())
+        };
+        let mut actions = actions.lock().await;
+        for (index, action) in actions.iter_mut().enumerate() {
+            let span = tracing::trace_span!("task action", task = %path, index);
+            match action
+                .execute(self.clone(), project.clone())
+                .instrument(span)
+                .await
+            {
+                Ok(()) => {}
+                Err(TaskError::StopAction(reason)) => {
+                    if let Some(reason) = reason {
+                        tracing::debug!("action stopped: {}", reason.kind);
+                    }
+                }
+                Err(TaskError::StopTask(reason)) => {
+                    if let Some(reason) = reason {
+                        tracing::debug!("task stopped: {}", reason.kind);
+                    }
+                    break;
+                }
+                Err(e @ TaskError::Fail(_)) => return Err(e),
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Convenience struct for stopping a task early.
@@ -121,12 +418,19 @@ where
     }
 }
 
+impl Debug for BoxTaskAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxTaskAction").finish_non_exhaustive()
+    }
+}
+
 impl TaskAction for BoxTaskAction {
     async fn execute(&mut self, task: Task, project: Project) -> Result {
         self.inner.get_mut()(task, project).await
     }
 }
 
+#[derive(Debug)]
 pub enum TaskError {
     Fail(Error),
     StopTask(Option<Error>),
@@ -139,9 +443,22 @@ impl TaskError {
     }
 }
 
+impl Display for TaskError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskError::Fail(e) => write!(f, "{}", e.kind),
+            TaskError::StopTask(_) => write!(f, "task stopped"),
+            TaskError::StopAction(_) => write!(f, "task action stopped"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorKind;
 
     async fn run(task: Task, project: Project) -> Result {
         Ok(())
@@ -152,7 +469,52 @@ mod tests {
         let mut task_action = from_fn(run);
         // let boxed = BoxTaskAction::new(task_action);
         let task = Task::new(":default");
-        let project = Project {};
+        let project = Project::new();
         let result = task_action.execute(task, project).await;
     }
+
+    #[tokio::test]
+    async fn test_execute_stops_task() {
+        let task = Task::new(":stopped");
+        task.do_last(from_fn(|_, _| async { TaskActions.stop_task() }))
+            .await
+            .unwrap();
+        task.do_last(from_fn(|_, _| async {
+            TaskActions::fail(ErrorKind::custom("unreachable"))
+        }))
+        .await
+        .unwrap();
+        assert!(task.execute(Project::new()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_relative_dependency() {
+        let task = Task::new(":sub:test");
+        assert_eq!(task.project_path().await, ":sub");
+        task.depends_on("compile").await.unwrap();
+        task.depends_on(":generate").await.unwrap();
+        assert_eq!(task.dependencies().await, [":sub:compile", ":generate"]);
+    }
+
+    #[tokio::test]
+    async fn test_configure_while_executing() {
+        let phase = PhaseTracker::starting_at(BuildPhase::Configuration);
+        let task = Task::with_phase(":late", phase.clone());
+        task.add_option(TaskOption::flag("all", "Everything."))
+            .await
+            .unwrap();
+        phase.enter(BuildPhase::Execution);
+        let illegal = |result: std::result::Result<(), ErrorKind>| {
+            matches!(result, Err(ErrorKind::IllegalPhase { .. }))
+        };
+        assert!(illegal(task.depends_on(":other").await));
+        assert!(illegal(task.add_output("out").await));
+        assert!(illegal(task.set_group("late").await));
+        assert!(illegal(
+            task.do_last(from_fn(|_, _| async { Ok(()) })).await
+        ));
+        let error = task.set_option("all", None).await.unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::IllegalPhase { .. }));
+        assert!(task.dependencies().await.is_empty());
+    }
 }
@@ -0,0 +1,71 @@
+//! Command line options declared by tasks
+
+/// An option a task accepts from the command line, such as `--all`
+#[derive(Debug, Clone)]
+pub struct TaskOption {
+    name: String,
+    description: String,
+    takes_value: bool,
+    value: Option<String>,
+}
+
+impl TaskOption {
+    /// Creates an option that is either present or absent
+    pub fn flag<S: AsRef<str>, D: AsRef<str>>(name: S, description: D) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            description: description.as_ref().to_string(),
+            takes_value: false,
+            value: None,
+        }
+    }
+
+    /// Creates an option that requires a value
+    pub fn with_value<S: AsRef<str>, D: AsRef<str>>(name: S, description: D) -> Self {
+        Self {
+            takes_value: true,
+            ..Self::flag(name, description)
+        }
+    }
+
+    /// Gets the name of this option, without the leading `--`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the description of this option
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Whether this option requires a value
+    pub fn takes_value(&self) -> bool {
+        self.takes_value
+    }
+
+    /// Gets the value of this option, if set. Flags have an empty value when set.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Unsets the value of this option
+    pub(crate) fn clear_value(&mut self) {
+        self.value = None;
+    }
+
+    /// Sets the value of this option
+    pub(crate) fn set_value(&mut self, value: Option<String>) -> Result<(), String> {
+        match (self.takes_value, value) {
+            (true, None) => Err("a value is required".to_string()),
+            (false, Some(_)) => Err("a value is not allowed".to_string()),
+            (true, Some(value)) => {
+                self.value = Some(value);
+                Ok(())
+            }
+            (false, None) => {
+                self.value = Some(String::new());
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Resolves task paths given on the command line, allowing abbreviated names.
+//!
+//! Each segment of a requested path is matched against the available names, trying in order
+//! an exact match, a case-insensitive match, a camel-case abbreviation (`cB` for `compileBuild`)
+//! and finally a case-insensitive prefix.
+
+use crate::error::ErrorKind;
+use std::collections::BTreeSet;
+
+/// The outcome of matching a name against a set of candidates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameMatch {
+    /// A single candidate matched
+    Found(String),
+    /// More than one candidate matched equally well
+    Ambiguous(Vec<String>),
+    /// No candidate matched, with similar names as suggestions
+    NotFound(Vec<String>),
+}
+
+/// Matches a pattern against a set of candidate names
+pub fn match_name<'a, I>(pattern: &str, candidates: I) -> NameMatch
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let candidates: BTreeSet<&str> = candidates.into_iter().collect();
+    let matchers: [fn(&str, &str) -> bool; 4] = [
+        |pattern, candidate| pattern == candidate,
+        |pattern, candidate| pattern.eq_ignore_ascii_case(candidate),
+        camel_case_matches,
+        |pattern, candidate| {
+            candidate
+                .to_lowercase()
+                .starts_with(&pattern.to_lowercase())
+        },
+    ];
+    for matcher in matchers {
+        let matches = candidates
+            .iter()
+            .filter(|candidate| matcher(pattern, candidate))
+            .map(|candidate| candidate.to_string())
+            .collect::<Vec<_>>();
+        match matches.len() {
+            0 => continue,
+            1 => return NameMatch::Found(matches.into_iter().next().unwrap()),
+            _ => return NameMatch::Ambiguous(matches),
+        }
+    }
+
+    let mut suggestions = candidates
+        .iter()
+        .map(|candidate| {
+            let distance = strsim::levenshtein(&pattern.to_lowercase(), &candidate.to_lowercase());
+            (distance, *candidate)
+        })
+        .filter(|(distance, candidate)| *distance <= pattern.len().max(candidate.len()) / 3 + 1)
+        .collect::<Vec<_>>();
+    suggestions.sort();
+    NameMatch::NotFound(
+        suggestions
+            .into_iter()
+            .map(|(_, candidate)| candidate.to_string())
+            .collect(),
+    )
+}
+
+/// Checks if `pattern` is a camel-case abbreviation of `candidate`.
+///
+/// The pattern is split into words at upper case letters and digits, and each word must be
+/// the prefix of a word in the candidate, in order.
+fn camel_case_matches(pattern: &str, candidate: &str) -> bool {
+    let mut rest = candidate;
+    for (i, word) in camel_case_words(pattern).into_iter().enumerate() {
+        if i > 0 {
+            rest = rest.trim_start_matches(|c: char| c.is_lowercase() || c.is_ascii_digit());
+        }
+        match rest.strip_prefix(word) {
+            Some(remaining) => rest = remaining,
+            None => return false,
+        }
+    }
+    true
+}
+
+fn camel_case_words(pattern: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start = 0;
+    for (i, c) in pattern.char_indices() {
+        if i > start && (c.is_uppercase() || c.is_ascii_digit()) {
+            words.push(&pattern[start..i]);
+            start = i;
+        }
+    }
+    if start < pattern.len() {
+        words.push(&pattern[start..]);
+    }
+    words
+}
+
+/// Selects the full path of a task from the available task paths.
+///
+/// Paths without a leading `:` are relative to the root project. Every project segment of the
+/// requested path is resolved before the task name itself.
+pub fn select_task_path<'a, I>(requested: &str, task_paths: I) -> Result<String, ErrorKind>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    select_task_path_from(requested, ":", task_paths)
+}
+
+/// Selects the full path of a task from the available task paths, resolving paths without a
+/// leading `:` relative to the project at `base_project`.
+pub fn select_task_path_from<'a, I>(
+    requested: &str,
+    base_project: &str,
+    task_paths: I,
+) -> Result<String, ErrorKind>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let task_paths: Vec<Vec<&str>> = task_paths
+        .into_iter()
+        .map(|path| path.trim_start_matches(':').split(':').collect())
+        .collect();
+    let segments: Vec<&str> = requested.trim_start_matches(':').split(':').collect();
+    let (task_name, project_segments) = segments.split_last().expect("split is never empty");
+
+    let mut resolved: Vec<String> = if requested.starts_with(':') {
+        vec![]
+    } else {
+        base_project
+            .split(':')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    for segment in project_segments {
+        let children = task_paths
+            .iter()
+            .filter(|path| path.len() > resolved.len() + 1 && is_within(path, &resolved))
+            .map(|path| path[resolved.len()]);
+        let project_path = || to_path(&resolved, segment);
+        match match_name(segment, children) {
+            NameMatch::Found(found) => resolved.push(found),
+            NameMatch::Ambiguous(candidates) => {
+                return Err(ErrorKind::AmbiguousProject {
+                    path: project_path(),
+                    candidates,
+                });
+            }
+            NameMatch::NotFound(suggestions) => {
+                return Err(ErrorKind::ProjectNotFound {
+                    path: project_path(),
+                    suggestions,
+                });
+            }
+        }
+    }
+
+    let names = task_paths
+        .iter()
+        .filter(|path| path.len() == resolved.len() + 1 && is_within(path, &resolved))
+        .map(|path| path[resolved.len()]);
+    let full_path = |name: &str| to_path(&resolved, name);
+    match match_name(task_name, names) {
+        NameMatch::Found(found) => Ok(full_path(&found)),
+        NameMatch::Ambiguous(candidates) => Err(ErrorKind::AmbiguousTask {
+            path: full_path(task_name),
+            candidates: candidates.iter().map(|c| full_path(c)).collect(),
+        }),
+        NameMatch::NotFound(suggestions) => Err(ErrorKind::TaskNotFound {
+            path: full_path(task_name),
+            suggestions: suggestions.iter().map(|c| full_path(c)).collect(),
+        }),
+    }
+}
+
+/// Checks if the segments of `path` start with the given project segments
+fn is_within(path: &[&str], project: &[String]) -> bool {
+    path.iter().zip(project).all(|(a, b)| a == b)
+}
+
+/// Creates an absolute path from resolved project segments and a final name
+fn to_path(project: &[String], name: &str) -> String {
+    let mut path = String::new();
+    for segment in project {
+        path.push(':');
+        path.push_str(segment);
+    }
+    path.push(':');
+    path.push_str(name);
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATHS: &[&str] = &[
+        ":compileBuild",
+        ":compileTest",
+        ":clean",
+        ":sub:test",
+        ":sub:build",
+    ];
+
+    #[test]
+    fn test_camel_case() {
+        assert!(camel_case_matches("cB", "compileBuild"));
+        assert!(camel_case_matches("comB", "compileBuild"));
+        assert!(!camel_case_matches("cB", "clean"));
+        assert_eq!(
+            select_task_path("cB", PATHS.iter().copied()).unwrap(),
+            ":compileBuild"
+        );
+    }
+
+    #[test]
+    fn test_prefix_in_subproject() {
+        assert_eq!(
+            select_task_path(":sub:tes", PATHS.iter().copied()).unwrap(),
+            ":sub:test"
+        );
+        assert_eq!(
+            select_task_path(":s:b", PATHS.iter().copied()).unwrap(),
+            ":sub:build"
+        );
+    }
+
+    #[test]
+    fn test_relative_to_project() {
+        assert_eq!(
+            select_task_path_from("tes", ":sub", PATHS.iter().copied()).unwrap(),
+            ":sub:test"
+        );
+        assert_eq!(
+            select_task_path_from(":clean", ":sub", PATHS.iter().copied()).unwrap(),
+            ":clean"
+        );
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let Err(ErrorKind::AmbiguousTask { candidates, .. }) =
+            select_task_path("comp", PATHS.iter().copied())
+        else {
+            panic!("expected ambiguous task")
+        };
+        assert_eq!(candidates, [":compileBuild", ":compileTest"]);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let Err(ErrorKind::TaskNotFound { suggestions, .. }) =
+            select_task_path("claen", PATHS.iter().copied())
+        else {
+            panic!("expected task not found")
+        };
+        assert_eq!(suggestions, [":clean"]);
+    }
+}
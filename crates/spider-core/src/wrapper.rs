@@ -0,0 +1,200 @@
+//! The `wrapper` task, which pins the version of spider a build runs with.
+//!
+//! The task writes a `spiderw` script and a properties file holding the pinned version into the
+//! root of the build. The script runs the distribution of that version cached in the spider
+//! user home, at `wrapper/dists/spider-<version>/bin/spider`, failing if it isn't installed.
+
+use crate::build_init::BUILD_SETUP_GROUP;
+use crate::error::{ErrorKind, Result};
+use crate::fs::user_home_dir;
+use crate::project::Project;
+use crate::task::options::TaskOption;
+use crate::task::{Task, TaskError, from_fn};
+use std::path::{Path, PathBuf};
+
+/// The name of the task that generates the wrapper
+pub const WRAPPER_TASK: &str = "wrapper";
+/// The file name of the wrapper script
+pub const WRAPPER_SCRIPT: &str = "spiderw";
+/// The path of the wrapper properties, relative to the root of the build
+pub const WRAPPER_PROPERTIES: &str = "spider/wrapper/spider-wrapper.properties";
+/// The property holding the pinned version
+pub const DISTRIBUTION_VERSION_PROPERTY: &str = "distributionVersion";
+/// The directory distributions are cached in, relative to the spider user home
+pub const DISTRIBUTIONS_DIR: &str = "wrapper/dists";
+
+/// Gets the path of the `spider` executable of a cached distribution
+pub fn distribution_executable(user_home: &Path, version: &str) -> PathBuf {
+    user_home
+        .join(DISTRIBUTIONS_DIR)
+        .join(format!("spider-{version}"))
+        .join("bin")
+        .join("spider")
+}
+
+/// The wrapper of a build, pinned to a spider version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wrapper {
+    version: String,
+}
+
+impl Wrapper {
+    /// Creates a wrapper running the given version of spider
+    pub fn new<S: AsRef<str>>(version: S) -> Self {
+        Self {
+            version: version.as_ref().to_string(),
+        }
+    }
+
+    /// Gets the version this wrapper runs
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Gets the files of this wrapper, relative to the root of the build
+    pub fn files(&self) -> Vec<(PathBuf, String)> {
+        let properties = format!(
+            "# The version of spider this build runs with, used by {WRAPPER_SCRIPT}\n\
+             {DISTRIBUTION_VERSION_PROPERTY}={version}\n",
+            version = self.version
+        );
+        vec![
+            (PathBuf::from(WRAPPER_SCRIPT), script()),
+            (PathBuf::from(WRAPPER_PROPERTIES), properties),
+        ]
+    }
+
+    /// Writes the files of this wrapper into `dir`, replacing any previous wrapper
+    pub fn generate(&self, dir: &Path) -> std::result::Result<Vec<PathBuf>, ErrorKind> {
+        let mut generated = vec![];
+        for (path, text) in self.files() {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, text)?;
+            generated.push(path);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = dir.join(WRAPPER_SCRIPT);
+            std::fs::set_permissions(script, std::fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(generated)
+    }
+}
+
+/// Creates the wrapper script, which reads the pinned version from the wrapper properties
+fn script() -> String {
+    format!(
+        r#"#!/bin/sh
+#
+# Runs the version of spider pinned in {WRAPPER_PROPERTIES}.
+# Generated by `spider {WRAPPER_TASK}`.
+
+set -e
+
+APP_HOME=$(cd "$(dirname "$0")" && pwd -P)
+PROPERTIES="$APP_HOME/{WRAPPER_PROPERTIES}"
+if [ ! -f "$PROPERTIES" ]; then
+    echo "{WRAPPER_SCRIPT}: $PROPERTIES is missing, run 'spider {WRAPPER_TASK}' to create it" >&2
+    exit 1
+fi
+
+VERSION=$(sed -n 's/^{DISTRIBUTION_VERSION_PROPERTY}=//p' "$PROPERTIES" | tr -d '[:space:]')
+if [ -z "$VERSION" ]; then
+    echo "{WRAPPER_SCRIPT}: no {DISTRIBUTION_VERSION_PROPERTY} is set in $PROPERTIES" >&2
+    exit 1
+fi
+
+SPIDER_HOME=${{SPIDER_USER_HOME:-$HOME/.spider}}
+SPIDER="$SPIDER_HOME/{DISTRIBUTIONS_DIR}/spider-$VERSION/bin/spider"
+if [ ! -x "$SPIDER" ]; then
+    echo "{WRAPPER_SCRIPT}: spider $VERSION is not installed, expected it at $SPIDER" >&2
+    exit 1
+fi
+
+exec "$SPIDER" "$@"
+"#
+    )
+}
+
+/// Registers the `wrapper` task in the given project, generating the wrapper into `dir`
+pub async fn register<P: AsRef<Path>>(project: &Project, dir: P) -> Result<Task> {
+    let dir = dir.as_ref().to_path_buf();
+    let task = project.tasks().await.register(WRAPPER_TASK).await?;
+    task.set_type_name("Wrapper").await?;
+    task.set_group(BUILD_SETUP_GROUP).await?;
+    task.set_description("Generates spider wrapper files.")
+        .await?;
+    task.add_option(TaskOption::with_value(
+        "version",
+        "The version of spider to run. Defaults to the version running this build.",
+    ))
+    .await?;
+    task.do_last(from_fn(move |task: Task, _: Project| {
+        let dir = dir.clone();
+        async move {
+            let version = task
+                .option("version")
+                .await
+                .and_then(|option| option.value().map(str::to_string))
+                .unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string());
+            let wrapper = Wrapper::new(&version);
+            let generated = wrapper.generate(&dir).map_err(TaskError::fail)?;
+            for path in generated {
+                println!("Created {}", path.display());
+            }
+            if let Some(user_home) = user_home_dir() {
+                let executable = distribution_executable(&user_home, &version);
+                if !executable.exists() {
+                    tracing::warn!(
+                        "spider {version} is not installed at {}, {WRAPPER_SCRIPT} will fail until it is",
+                        executable.display()
+                    );
+                }
+            }
+            Ok(())
+        }
+    }))
+    .await?;
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_runs_cached_distribution() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Command;
+
+        let build = tempfile::tempdir().unwrap();
+        let user_home = tempfile::tempdir().unwrap();
+        Wrapper::new("1.2.3").generate(build.path()).unwrap();
+        let properties = std::fs::read_to_string(build.path().join(WRAPPER_PROPERTIES)).unwrap();
+        assert!(properties.contains("distributionVersion=1.2.3\n"));
+
+        let run = || {
+            Command::new(build.path().join(WRAPPER_SCRIPT))
+                .arg("build")
+                .env("SPIDER_USER_HOME", user_home.path())
+                .output()
+                .unwrap()
+        };
+        let missing = run();
+        assert!(!missing.status.success());
+        assert!(String::from_utf8_lossy(&missing.stderr).contains("spider 1.2.3 is not installed"));
+
+        let executable = distribution_executable(user_home.path(), "1.2.3");
+        std::fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        std::fs::write(&executable, "#!/bin/sh\necho \"spider $*\"\n").unwrap();
+        std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let output = run();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "spider build\n");
+    }
+}
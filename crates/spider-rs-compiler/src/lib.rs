@@ -1,5 +1,266 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+//! # `spider-rs-compiler`
+//! Compiles spider scripts.
+//!
+//! A spider script is a sequence of rust statements, such as
+//!
+//! ```text
+//! plugins! {
+//!     id "settings" version "1.0.0",
+//! }
+//!
+//! rootProject.name = "mockProject";
+//! include(":app", ":lib");
+//! ```
+//!
+//! Compiling a script checks its syntax and produces a [`Script`] of [`Statement`]s, leaving it to
+//! the evaluator of each kind of script to decide what the statements mean.
+
+use proc_macro2::Span;
+use std::fmt::{Display, Formatter};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{Block, Lit, LitStr, Member, Stmt, Token};
+use thiserror::Error;
+
+/// A compiled script
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    statements: Vec<Statement>,
+}
+
+impl Script {
+    /// Gets the statements of this script, in order
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+}
+
+/// A statement of a script, with the line it starts on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub line: usize,
+    pub kind: StatementKind,
+}
+
+/// The kinds of statement a script can contain
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    /// Requests plugins, with `plugins! { id "name" version "1.0.0", ... }`
+    Plugins(Vec<PluginRequest>),
+    /// Assigns a value, such as `rootProject.name = "name";`
+    Assign { target: Expr, value: Expr },
+    /// Evaluates an expression, such as `include(":app");`
+    Expr(Expr),
+}
+
+/// A plugin requested by a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginRequest {
+    pub id: String,
+    pub version: Option<String>,
+}
+
+/// The expressions a script can contain
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A string literal
+    Str(String),
+    /// An integer literal
+    Int(i64),
+    /// A boolean literal
+    Bool(bool),
+    /// A name, such as `rootProject`
+    Ident(String),
+    /// A field of a value, such as `rootProject.name`
+    Field { base: Box<Expr>, name: String },
+    /// A call of a function, such as `include(":app")`
+    Call { function: String, args: Vec<Expr> },
+    /// A call of a method, such as `project(":app").file("src")`
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    /// Gets the value of a string literal
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Expr::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let args = |args: &[Expr]| {
+            args.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Expr::Str(value) => write!(f, "{value:?}"),
+            Expr::Int(value) => write!(f, "{value}"),
+            Expr::Bool(value) => write!(f, "{value}"),
+            Expr::Ident(name) => write!(f, "{name}"),
+            Expr::Field { base, name } => write!(f, "{base}.{name}"),
+            Expr::Call { function, args: a } => write!(f, "{function}({})", args(a)),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args: a,
+            } => write!(f, "{receiver}.{method}({})", args(a)),
+        }
+    }
+}
+
+/// A script could not be compiled
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}, column {column}: {message}")]
+pub struct CompileError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl From<syn::Error> for CompileError {
+    fn from(error: syn::Error) -> Self {
+        let start = error.span().start();
+        Self {
+            // scripts are compiled within a block starting on a line of its own
+            line: start.line.saturating_sub(1),
+            column: start.column + 1,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Compiles the text of a script
+pub fn compile(text: &str) -> Result<Script, CompileError> {
+    let block: Block = syn::parse_str(&format!("{{\n{text}\n}}"))?;
+    let statements = block
+        .stmts
+        .iter()
+        .map(statement)
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(Script { statements })
+}
+
+fn statement(stmt: &Stmt) -> syn::Result<Statement> {
+    let kind = match stmt {
+        Stmt::Macro(stmt) => macro_statement(&stmt.mac)?,
+        Stmt::Item(syn::Item::Macro(item)) => macro_statement(&item.mac)?,
+        Stmt::Expr(syn::Expr::Assign(assign), _) => StatementKind::Assign {
+            target: expr(&assign.left)?,
+            value: expr(&assign.right)?,
+        },
+        Stmt::Expr(syn::Expr::Macro(expr), _) => macro_statement(&expr.mac)?,
+        Stmt::Expr(e, _) => StatementKind::Expr(expr(e)?),
+        other => return Err(unsupported(other.span(), "statement")),
+    };
+    Ok(Statement {
+        line: stmt.span().start().line.saturating_sub(1),
+        kind,
+    })
+}
+
+fn macro_statement(mac: &syn::Macro) -> syn::Result<StatementKind> {
+    if mac.path.is_ident("plugins") {
+        let plugins: PluginRequests = mac.parse_body()?;
+        Ok(StatementKind::Plugins(plugins.0))
+    } else {
+        Err(syn::Error::new(
+            mac.path.span(),
+            format!(
+                "unknown macro `{}!`",
+                quote::ToTokens::to_token_stream(&mac.path)
+            ),
+        ))
+    }
+}
+
+fn expr(e: &syn::Expr) -> syn::Result<Expr> {
+    match e {
+        syn::Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(value) => Ok(Expr::Str(value.value())),
+            Lit::Int(value) => Ok(Expr::Int(value.base10_parse()?)),
+            Lit::Bool(value) => Ok(Expr::Bool(value.value)),
+            other => Err(unsupported(other.span(), "literal")),
+        },
+        syn::Expr::Path(path) => Ok(Expr::Ident(ident(&path.path)?)),
+        syn::Expr::Field(field) => match &field.member {
+            Member::Named(name) => Ok(Expr::Field {
+                base: Box::new(expr(&field.base)?),
+                name: name.to_string(),
+            }),
+            Member::Unnamed(index) => Err(unsupported(index.span(), "tuple field")),
+        },
+        syn::Expr::Call(call) => {
+            let syn::Expr::Path(function) = &*call.func else {
+                return Err(unsupported(call.func.span(), "function"));
+            };
+            Ok(Expr::Call {
+                function: ident(&function.path)?,
+                args: call.args.iter().map(expr).collect::<syn::Result<_>>()?,
+            })
+        }
+        syn::Expr::MethodCall(call) => Ok(Expr::MethodCall {
+            receiver: Box::new(expr(&call.receiver)?),
+            method: call.method.to_string(),
+            args: call.args.iter().map(expr).collect::<syn::Result<_>>()?,
+        }),
+        syn::Expr::Paren(paren) => expr(&paren.expr),
+        other => Err(unsupported(other.span(), "expression")),
+    }
+}
+
+fn ident(path: &syn::Path) -> syn::Result<String> {
+    path.get_ident()
+        .map(ToString::to_string)
+        .ok_or_else(|| unsupported(path.span(), "path"))
+}
+
+fn unsupported(span: Span, what: &str) -> syn::Error {
+    syn::Error::new(
+        span,
+        format!("this {what} is not supported in spider scripts"),
+    )
+}
+
+/// The body of `plugins!`
+struct PluginRequests(Vec<PluginRequest>);
+
+impl Parse for PluginRequests {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut requests = vec![];
+        while !input.is_empty() {
+            let keyword: syn::Ident = input.parse()?;
+            if keyword != "id" {
+                return Err(syn::Error::new(keyword.span(), "expected `id`"));
+            }
+            let id: LitStr = input.parse()?;
+            let version = if input.peek(syn::Ident) {
+                let keyword: syn::Ident = input.parse()?;
+                if keyword != "version" {
+                    return Err(syn::Error::new(keyword.span(), "expected `version`"));
+                }
+                Some(input.parse::<LitStr>()?.value())
+            } else {
+                None
+            };
+            requests.push(PluginRequest {
+                id: id.value(),
+                version,
+            });
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(Self(requests))
+    }
 }
 
 #[cfg(test)]
@@ -7,8 +268,39 @@ mod tests {
     use super::*;
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn test_compile_settings() {
+        let script = compile(
+            "plugins! {\n    id \"settings\" version \"1.0.0\",\n}\n\n\
+             rootProject.name = \"mockProject\";\n\
+             include(\":a\", \":a:b\");\n",
+        )
+        .unwrap();
+        let statements = script.statements();
+        assert_eq!(
+            statements[0].kind,
+            StatementKind::Plugins(vec![PluginRequest {
+                id: "settings".to_string(),
+                version: Some("1.0.0".to_string()),
+            }])
+        );
+        assert_eq!(statements[1].line, 5);
+        let StatementKind::Assign { target, value } = &statements[1].kind else {
+            panic!("expected an assignment")
+        };
+        assert_eq!(target.to_string(), "rootProject.name");
+        assert_eq!(value.as_str(), Some("mockProject"));
+        assert_eq!(
+            statements[2].kind,
+            StatementKind::Expr(Expr::Call {
+                function: "include".to_string(),
+                args: vec![Expr::Str(":a".to_string()), Expr::Str(":a:b".to_string())],
+            })
+        );
+    }
+
+    #[test]
+    fn test_compile_error_location() {
+        let error = compile("include(\":a\");\nlet x = ;\n").unwrap_err();
+        assert_eq!(error.line, 2);
     }
 }
@@ -0,0 +1,389 @@
+//! Command line arguments of the `spider` binary
+
+use crate::console::ConsoleMode;
+use crate::logging::LogLevel;
+use clap::{CommandFactory, Parser};
+use spider_core::error::ShowStacktrace;
+use spider_core::invocation::start_parameter::StartParameter;
+use spider_core::wrapper::WRAPPER_TASK;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Options of built-in tasks named like a global option, by the name of their task. Following
+/// their task, these are passed to it instead of being read as the global option.
+const TASK_OPTIONS: &[(&str, &str)] = &[(WRAPPER_TASK, "--version")];
+
+/// Spider build system
+#[derive(Debug, Parser)]
+#[command(name = "spider", version, about)]
+pub struct Args {
+    /// Prints the tasks that would be executed, without executing them
+    #[arg(short = 'm', long)]
+    pub dry_run: bool,
+
+    /// Continues executing tasks that don't depend on a failed task
+    #[arg(long = "continue")]
+    pub continue_on_failure: bool,
+
+    /// Logs errors only
+    #[arg(short, long, group = "log_level")]
+    pub quiet: bool,
+
+    /// Logs warnings and errors only
+    #[arg(short, long, group = "log_level")]
+    pub warn: bool,
+
+    /// Logs informational messages
+    #[arg(short, long, group = "log_level")]
+    pub info: bool,
+
+    /// Logs debug messages
+    #[arg(short, long, group = "log_level")]
+    pub debug: bool,
+
+    /// Shows the stack trace of build errors
+    #[arg(short, long, group = "stacktrace_level")]
+    pub stacktrace: bool,
+
+    /// Shows every frame of the stack trace of build errors
+    #[arg(short = 'S', long, group = "stacktrace_level")]
+    pub full_stacktrace: bool,
+
+    /// How build progress is shown
+    #[arg(long, value_enum, default_value_t = ConsoleMode::Auto)]
+    pub console: ConsoleMode,
+
+    /// Writes the events of the build to this file as newline-delimited JSON
+    #[arg(long, value_name = "FILE")]
+    pub events: Option<PathBuf>,
+
+    /// Writes a performance profile of the build to this file in the Chrome Trace Event format.
+    /// Profiled builds run in this process instead of in the daemon.
+    #[arg(long, value_name = "FILE")]
+    pub profile: Option<PathBuf>,
+
+    /// Keeps running, and reruns the tasks affected whenever their inputs or a build script
+    /// change
+    #[arg(short = 't', long)]
+    pub continuous: bool,
+
+    /// Only configures the root project and the projects the requested tasks need
+    #[arg(long)]
+    pub configure_on_demand: bool,
+
+    /// Runs the build in the daemon, starting one if none is running
+    #[arg(long, conflicts_with = "no_daemon")]
+    pub daemon: bool,
+
+    /// Runs the build in this process, even if `SPIDER_DAEMON=true`
+    #[arg(long)]
+    pub no_daemon: bool,
+
+    /// Stops the running daemon
+    #[arg(long)]
+    pub stop: bool,
+
+    /// Shows the state of the running daemon
+    #[arg(long)]
+    pub status: bool,
+
+    /// Runs the daemon serving builds, instead of a build
+    #[arg(long, hide = true)]
+    pub daemon_server: bool,
+
+    /// Lists the tasks of the build for shell completion
+    #[arg(long, hide = true)]
+    pub complete_tasks: bool,
+
+    /// Sets a project property, readable with `providers.project_property(key)`
+    #[arg(short = 'P', long = "project-prop", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub project_properties: Vec<(String, String)>,
+
+    /// Sets a system property, readable with `providers.system_property(key)`
+    #[arg(short = 'D', long = "system-prop", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub system_properties: Vec<(String, String)>,
+
+    /// Starts the build from this directory instead of the working directory
+    #[arg(short = 'p', long, value_name = "DIR")]
+    pub project_dir: Option<PathBuf>,
+
+    /// Uses this settings script instead of searching for `settings.spider.rs`
+    #[arg(short = 'c', long, value_name = "FILE")]
+    pub settings_file: Option<PathBuf>,
+
+    /// Reads init scripts and properties from this spider user home instead of the default one
+    #[arg(short = 'g', long, value_name = "DIR")]
+    pub user_home: Option<PathBuf>,
+
+    /// Evaluates this init script before the init scripts of the spider user home
+    #[arg(short = 'I', long = "init-script", value_name = "FILE")]
+    pub init_scripts: Vec<PathBuf>,
+
+    /// Excludes a task, and any dependencies only it needs, from the build
+    #[arg(short = 'x', long = "exclude-task", value_name = "TASK")]
+    pub exclude_tasks: Vec<String>,
+
+    /// The tasks to run, in order, each optionally followed by its options
+    #[arg(value_name = "TASK", allow_hyphen_values = true)]
+    pub tasks: Vec<String>,
+}
+
+impl Args {
+    /// Parses arguments, with global options allowed anywhere between tasks.
+    ///
+    /// Arguments that aren't global options are passed through to task selection in order, so
+    /// that options following a task, like `tasks --all`, are options of that task. An option
+    /// named like a global option is passed to the task before it when that task declares it,
+    /// like `wrapper --version=1.0`.
+    pub fn parse_args<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Self::command();
+        command.build();
+
+        let mut args = args.into_iter().map(Into::into);
+        let mut globals: Vec<OsString> = args.next().into_iter().collect();
+        let mut tasks: Vec<OsString> = vec![];
+        let mut current_task: Option<String> = None;
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                tasks.extend(args.by_ref());
+                break;
+            }
+            let lossy = arg.to_string_lossy().into_owned();
+            if current_task
+                .as_deref()
+                .is_some_and(|task| declares_option(task, &lossy))
+            {
+                tasks.push(arg);
+                if !lossy.contains('=') {
+                    tasks.extend(args.next());
+                }
+                continue;
+            }
+            match global_option(&command, &lossy) {
+                Some(takes_value) => {
+                    globals.extend(split_attached_value(&command, arg));
+                    if takes_value {
+                        globals.extend(args.next());
+                    }
+                }
+                None => {
+                    if !lossy.starts_with('-') {
+                        current_task = Some(lossy);
+                    }
+                    tasks.push(arg);
+                }
+            }
+        }
+        globals.push("--".into());
+        globals.extend(tasks);
+        Self::parse_from(globals)
+    }
+
+    /// Gets the level to log at
+    pub fn log_level(&self) -> LogLevel {
+        if self.quiet {
+            LogLevel::Quiet
+        } else if self.warn {
+            LogLevel::Warn
+        } else if self.info {
+            LogLevel::Info
+        } else if self.debug {
+            LogLevel::Debug
+        } else {
+            LogLevel::Lifecycle
+        }
+    }
+
+    /// Gets how much of the stack trace of errors to show
+    pub fn show_stacktrace(&self) -> ShowStacktrace {
+        if self.full_stacktrace {
+            ShowStacktrace::Full
+        } else if self.stacktrace {
+            ShowStacktrace::Always
+        } else {
+            ShowStacktrace::Never
+        }
+    }
+
+    /// Creates the start parameter for a build from these arguments
+    pub fn start_parameter(&self) -> StartParameter {
+        let mut parameter = StartParameter::new();
+        parameter.set_task_names(&self.tasks);
+        parameter.set_excluded_task_names(&self.exclude_tasks);
+        parameter.set_dry_run(self.dry_run);
+        parameter.set_continue_on_failure(self.continue_on_failure);
+        parameter.set_configure_on_demand(self.configure_on_demand);
+        parameter.set_project_dir(self.project_dir.as_ref());
+        parameter.set_settings_file(self.settings_file.as_ref());
+        parameter.set_init_scripts(&self.init_scripts);
+        parameter.set_user_home(self.user_home.as_ref());
+        for (key, value) in &self.project_properties {
+            parameter.set_project_property(key, value);
+        }
+        for (key, value) in &self.system_properties {
+            parameter.set_system_property(key, value);
+        }
+        parameter
+    }
+}
+
+/// Parses a `key=value` property. A property without a value is set to the empty string.
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg.split_once('=').unwrap_or((arg, ""));
+    if key.is_empty() {
+        return Err(format!("property {arg:?} has no key"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Splits a short option with an attached value, such as `-Pkey=value`, into the option and its
+/// value, as clap would otherwise read it as a positional argument
+fn split_attached_value(command: &clap::Command, arg: OsString) -> Vec<OsString> {
+    let lossy = arg.to_string_lossy();
+    let short = lossy
+        .strip_prefix('-')
+        .filter(|short| !short.starts_with('-'));
+    let Some((flag, value)) = short.and_then(|short| {
+        let flag = short.chars().next()?;
+        Some((flag, &short[flag.len_utf8()..]))
+    }) else {
+        return vec![arg];
+    };
+    let takes_value = command
+        .get_arguments()
+        .any(|a| a.get_short() == Some(flag) && a.get_action().takes_values());
+    if takes_value && !value.is_empty() {
+        vec![format!("-{flag}").into(), value.into()]
+    } else {
+        vec![arg]
+    }
+}
+
+/// Checks if `arg` is an option of a built-in task that `task` names, but that has the name of a
+/// global option
+fn declares_option(task: &str, arg: &str) -> bool {
+    let name = task.rsplit(':').next().unwrap_or(task);
+    let option = arg.split_once('=').map_or(arg, |(option, _)| option);
+    TASK_OPTIONS.contains(&(name, option))
+}
+
+/// Checks if `arg` is a global option, returning whether the next argument is its value
+fn global_option(command: &clap::Command, arg: &str) -> Option<bool> {
+    let takes_separate_value =
+        |arg: &clap::Arg, attached: bool| !attached && arg.get_action().takes_values();
+    if let Some(long) = arg.strip_prefix("--") {
+        let (name, attached) = match long.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (long, false),
+        };
+        command
+            .get_arguments()
+            .find(|a| a.get_long() == Some(name))
+            .map(|a| takes_separate_value(a, attached))
+    } else if let Some(short) = arg.strip_prefix('-') {
+        let mut chars = short.chars();
+        let flag = chars.next()?;
+        command
+            .get_arguments()
+            .find(|a| a.get_short() == Some(flag))
+            .map(|a| takes_separate_value(a, !chars.as_str().is_empty()))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_tasks() {
+        let args = Args::parse_args(["spider", "clean", ":build"]);
+        assert_eq!(args.start_parameter().task_names(), ["clean", ":build"]);
+    }
+
+    #[test]
+    fn test_task_options_are_kept() {
+        let args = Args::parse_args(["spider", "tasks", "--all"]);
+        assert_eq!(args.start_parameter().task_names(), ["tasks", "--all"]);
+    }
+
+    #[test]
+    fn test_version_after_task() {
+        let args = Args::parse_args(["spider", "wrapper", "--version", "1.0"]);
+        assert_eq!(
+            args.start_parameter().task_names(),
+            ["wrapper", "--version", "1.0"]
+        );
+        let args = Args::parse_args(["spider", "wrapper", "--version=1.0"]);
+        assert_eq!(
+            args.start_parameter().task_names(),
+            ["wrapper", "--version=1.0"]
+        );
+    }
+
+    #[test]
+    fn test_global_options_after_task() {
+        let args = Args::parse_args(["spider", "build", "-x", "test", "clean"]);
+        assert_eq!(args.start_parameter().task_names(), ["build", "clean"]);
+        assert_eq!(args.start_parameter().excluded_task_names(), ["test"]);
+        let args = Args::parse_args(["spider", "tasks", "--continue", "-m"]);
+        assert_eq!(args.start_parameter().task_names(), ["tasks"]);
+        assert!(args.start_parameter().is_continue_on_failure());
+        assert!(args.start_parameter().is_dry_run());
+    }
+
+    #[test]
+    fn test_global_options() {
+        let args = Args::parse_args(["spider", "build", "-m", "-x", "test"]);
+        assert!(args.start_parameter().is_dry_run());
+        assert_eq!(args.start_parameter().task_names(), ["build"]);
+        assert_eq!(args.start_parameter().excluded_task_names(), ["test"]);
+    }
+
+    #[test]
+    fn test_properties() {
+        let args = Args::parse_args(["spider", "build", "-Pversion=1.0", "-D", "debug"]);
+        let parameter = args.start_parameter();
+        assert_eq!(parameter.task_names(), ["build"]);
+        assert_eq!(parameter.project_properties()["version"], "1.0");
+        assert_eq!(parameter.system_properties()["debug"], "");
+    }
+
+    #[test]
+    fn test_log_level() {
+        let args = Args::parse_args(["spider", "build", "-d", "--stacktrace"]);
+        assert_eq!(args.log_level(), LogLevel::Debug);
+        assert_eq!(args.show_stacktrace(), ShowStacktrace::Always);
+        assert_eq!(
+            Args::parse_args(["spider"]).log_level(),
+            LogLevel::Lifecycle
+        );
+    }
+
+    #[test]
+    fn test_project_dir() {
+        let args = Args::parse_args([
+            "spider",
+            "build",
+            "-p",
+            "sub",
+            "-csettings.spider.rs",
+            "-I",
+            "ci.spider.rs",
+        ]);
+        let parameter = args.start_parameter();
+        assert_eq!(parameter.task_names(), ["build"]);
+        assert_eq!(parameter.project_dir(), Some(Path::new("sub")));
+        assert_eq!(
+            parameter.settings_file(),
+            Some(Path::new("settings.spider.rs"))
+        );
+        assert_eq!(parameter.init_scripts(), [Path::new("ci.spider.rs")]);
+    }
+}
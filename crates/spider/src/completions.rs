@@ -0,0 +1,250 @@
+//! Shell completion scripts, completing options and the task paths of the build
+
+use spider_core::error::Result;
+use spider_core::invocation::spider::Spider;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// The command line argument that prints a completion script
+pub const COMPLETIONS_COMMAND: &str = "completions";
+/// The hidden option the completion scripts use to list the tasks of the build
+pub const COMPLETE_TASKS_OPTION: &str = "--complete-tasks";
+/// The file task paths are cached in, relative to the root of the build
+const CACHE_FILE: &str = ".spider/task-paths.cache";
+
+/// The shells completion scripts can be generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Arguments of `spider completions`
+#[derive(Debug, clap::Parser)]
+#[command(name = "spider completions")]
+pub struct CompletionsArgs {
+    /// The shell to generate the completion script for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+/// An option of the `spider` command
+struct Flag {
+    names: Vec<String>,
+    help: String,
+}
+
+/// Collects the visible options of a command
+fn flags(command: &clap::Command) -> Vec<Flag> {
+    let mut command = command.clone();
+    command.build();
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .map(|arg| {
+            let mut names = vec![];
+            names.extend(arg.get_short().map(|short| format!("-{short}")));
+            names.extend(arg.get_long().map(|long| format!("--{long}")));
+            Flag {
+                names,
+                help: arg.get_help().map(|h| h.to_string()).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Generates the completion script for a shell
+pub fn generate(shell: Shell, command: &clap::Command) -> String {
+    let flags = flags(command);
+    let mut script = String::new();
+    match shell {
+        Shell::Bash => {
+            let names = flags
+                .iter()
+                .flat_map(|flag| flag.names.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" ");
+            write!(
+                script,
+                r#"_spider() {{
+    local line="${{COMP_LINE:0:$COMP_POINT}}"
+    local cur="${{line##* }}"
+    local candidates
+    if [[ "$cur" == -* ]]; then
+        candidates="{names}"
+    else
+        candidates="$("${{COMP_WORDS[0]}}" {COMPLETE_TASKS_OPTION} 2>/dev/null)"
+    fi
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    # bash splits words at ':', so only the part after the last one is replaced
+    if [[ "$cur" == *:* ]]; then
+        local prefix="${{cur%:*}}:"
+        COMPREPLY=("${{COMPREPLY[@]#"$prefix"}}")
+    fi
+}}
+complete -F _spider spider spiderw
+"#
+            )
+            .unwrap();
+        }
+        Shell::Zsh => {
+            let described = flags
+                .iter()
+                .flat_map(|flag| {
+                    flag.names
+                        .iter()
+                        .map(|name| format!("        '{name}:{}'", zsh_escape(&flag.help)))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            write!(
+                script,
+                r#"#compdef spider spiderw
+_spider() {{
+    local -a flags tasks
+    flags=(
+{described}
+    )
+    if [[ "$PREFIX" == -* ]]; then
+        _describe 'option' flags
+    else
+        tasks=(${{(f)"$(${{words[1]}} {COMPLETE_TASKS_OPTION} 2>/dev/null)"}})
+        compadd -a tasks
+    fi
+}}
+if [[ "$funcstack[1]" == "_spider" ]]; then
+    _spider "$@"
+else
+    compdef _spider spider spiderw
+fi
+"#
+            )
+            .unwrap();
+        }
+        Shell::Fish => {
+            for command in ["spider", "spiderw"] {
+                writeln!(script, "complete -c {command} -f").unwrap();
+                for flag in &flags {
+                    write!(script, "complete -c {command}").unwrap();
+                    for name in &flag.names {
+                        match name.strip_prefix("--") {
+                            Some(long) => write!(script, " -l {long}").unwrap(),
+                            None => write!(script, " -s {}", &name[1..]).unwrap(),
+                        }
+                    }
+                    writeln!(script, " -d '{}'", flag.help.replace('\'', "\\'")).unwrap();
+                }
+                writeln!(
+                    script,
+                    "complete -c {command} -n 'not string match -q -- \"-*\" (commandline -ct)' \
+                     -a '({command} {COMPLETE_TASKS_OPTION} 2>/dev/null)'"
+                )
+                .unwrap();
+            }
+        }
+    }
+    script
+}
+
+fn zsh_escape(help: &str) -> String {
+    help.replace('\'', "'\\''").replace(':', "\\:")
+}
+
+/// Lists the tasks of the build for completion, one per line.
+///
+/// Tasks of the root project are listed by name, and all others by their full path. The list is
+/// cached in the root of the build along with the scripts and properties files of the build, until
+/// one of them changes.
+pub async fn complete_tasks(spider: &mut Spider) -> Result<String> {
+    let cache = spider.root_dir().join(CACHE_FILE);
+    let cached = std::fs::read_to_string(&cache).unwrap_or_default();
+    if let Some(paths) = cached_task_paths(&cached) {
+        return Ok(paths.to_string());
+    }
+
+    let mut paths = String::new();
+    for path in spider.task_paths().await? {
+        let path = match path.strip_prefix(':') {
+            Some(name) if !name.contains(':') => name,
+            _ => &path,
+        };
+        writeln!(paths, "{path}").unwrap();
+    }
+    let scripts = spider.script_paths();
+    let mut contents = format!("{}\n{}\n", fingerprint(&scripts), scripts.len());
+    for script in &scripts {
+        writeln!(contents, "{}", script.display()).unwrap();
+    }
+    if let Some(parent) = cache.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&cache, contents + &paths)?;
+    Ok(paths)
+}
+
+/// Gets the task paths of a cache, unless a script it was computed from changed since. The cache
+/// holds a fingerprint of the scripts, the number of scripts and their paths, then the task
+/// paths.
+fn cached_task_paths(cached: &str) -> Option<&str> {
+    let (expected, rest) = cached.split_once('\n')?;
+    let (count, mut rest) = rest.split_once('\n')?;
+    let mut scripts = vec![];
+    for _ in 0..count.parse::<usize>().ok()? {
+        let (script, remaining) = rest.split_once('\n')?;
+        scripts.push(PathBuf::from(script));
+        rest = remaining;
+    }
+    (fingerprint(&scripts) == expected).then_some(rest)
+}
+
+/// Fingerprints scripts by their paths and modification times
+fn fingerprint(scripts: &[PathBuf]) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    for script in scripts {
+        let modified = std::fs::metadata(script).and_then(|m| m.modified()).ok();
+        (script, modified).hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_scripts_complete_flags_and_tasks() {
+        let command = Args::command();
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = generate(shell, &command);
+            assert!(script.contains("dry-run"), "{shell:?}");
+            assert!(script.contains(COMPLETE_TASKS_OPTION), "{shell:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_until_a_script_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("settings.spider.rs"), "include(\":app\");\n").unwrap();
+        std::fs::create_dir(root.join("app")).unwrap();
+        let mut spider = Spider::in_path(root).unwrap();
+        spider.set_user_home(root.join(".spider-home"));
+
+        let paths = complete_tasks(&mut spider).await.unwrap();
+        assert!(paths.lines().any(|path| path == ":app:tasks"));
+        let cached = std::fs::read_to_string(root.join(CACHE_FILE)).unwrap();
+        assert_eq!(cached_task_paths(&cached), Some(paths.as_str()));
+
+        let build_script = std::fs::File::create(root.join("app/build.spider.rs")).unwrap();
+        build_script
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(cached_task_paths(&cached), None);
+    }
+}
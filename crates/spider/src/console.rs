@@ -0,0 +1,329 @@
+//! Renders the progress of a build on the terminal
+
+use crate::events::JsonEventWriter;
+use spider_core::events::{BuildEvent, BuildListener, TaskOutcome};
+use std::fs::File;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use terminal_size::{Height, Width, terminal_size_of};
+
+/// How build progress is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ConsoleMode {
+    /// One line per task, suitable for logs
+    Plain,
+    /// A live status area below the build output
+    Rich,
+    /// Rich when stdout is a terminal, plain otherwise
+    #[default]
+    Auto,
+    /// Newline-delimited JSON events on stdout, with all other output on stderr
+    Json,
+}
+
+/// The number of running tasks shown in the status area. Tasks run one at a time.
+const RUNNING_LINES: usize = 1;
+/// How often the status area is redrawn while nothing happens
+const TICK: Duration = Duration::from_millis(100);
+/// The width of the progress bar, without its brackets
+const BAR_WIDTH: usize = 13;
+
+/// The console of a build
+pub struct Console {
+    mode: ConsoleMode,
+    rich: Option<Arc<RichConsole>>,
+    ticker: Option<JoinHandle<()>>,
+    /// The standard output of the process, when it was moved to stderr for the JSON events
+    events: Option<File>,
+}
+
+impl Console {
+    /// Creates the console for the given mode, falling back to plain output when stdout isn't a
+    /// terminal that can show a status area
+    pub fn new(mode: ConsoleMode) -> Self {
+        let rich = match mode {
+            ConsoleMode::Plain | ConsoleMode::Json => None,
+            ConsoleMode::Rich => RichConsole::start(),
+            ConsoleMode::Auto => {
+                let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+                if std::io::stdout().is_terminal() && !dumb {
+                    RichConsole::start()
+                } else {
+                    None
+                }
+            }
+        }
+        .map(Arc::new);
+        let ticker = rich.clone().map(|rich| {
+            std::thread::spawn(move || {
+                while !rich.stopped.load(Ordering::Relaxed) {
+                    rich.draw();
+                    std::thread::sleep(TICK);
+                }
+            })
+        });
+        let events = match mode {
+            ConsoleMode::Json => take_stdout()
+                .inspect_err(|e| tracing::warn!("could not move output to stderr: {e}"))
+                .ok(),
+            _ => None,
+        };
+        Self {
+            mode,
+            rich,
+            ticker,
+            events,
+        }
+    }
+
+    /// Creates a listener showing the progress of builds on this console
+    pub fn listener(&self) -> Box<dyn Fn(&BuildEvent) + Send + Sync> {
+        match &self.rich {
+            Some(rich) => {
+                let rich = rich.clone();
+                Box::new(move |event: &BuildEvent| rich.on_event(event))
+            }
+            None if self.mode == ConsoleMode::Json => {
+                let stdout: Box<dyn Write + Send> =
+                    match self.events.as_ref().and_then(|file| file.try_clone().ok()) {
+                        Some(file) => Box::new(file),
+                        None => Box::new(std::io::stdout()),
+                    };
+                let writer = JsonEventWriter::new(stdout);
+                Box::new(move |event: &BuildEvent| writer.on_event(event))
+            }
+            None => Box::new(plain_event),
+        }
+    }
+
+    /// Removes the status area, leaving only the build output
+    pub fn finish(mut self) {
+        if let Some(rich) = &self.rich {
+            rich.stopped.store(true, Ordering::Relaxed);
+            if let Some(ticker) = self.ticker.take() {
+                let _ = ticker.join();
+            }
+            rich.clear();
+        }
+        if let Some(events) = self.events.take() {
+            restore_stdout(events);
+        }
+    }
+}
+
+/// Points the standard output of the process at its standard error, returning the original
+/// standard output. Anything printed by the build, like reports and dry runs, then stays out of
+/// the JSON events.
+#[cfg(unix)]
+fn take_stdout() -> std::io::Result<File> {
+    use std::os::fd::FromRawFd;
+
+    std::io::stdout().flush()?;
+    let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if saved == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: dup returned a new descriptor that nothing else owns
+    let saved = unsafe { File::from_raw_fd(saved) };
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(saved)
+}
+
+#[cfg(not(unix))]
+fn take_stdout() -> std::io::Result<File> {
+    Err(std::io::Error::other("not supported on this platform"))
+}
+
+/// Points the standard output of the process back at `stdout`
+#[cfg(unix)]
+fn restore_stdout(stdout: File) {
+    use std::os::fd::AsRawFd;
+
+    let _ = std::io::stdout().flush();
+    unsafe { libc::dup2(stdout.as_raw_fd(), libc::STDOUT_FILENO) };
+}
+
+#[cfg(not(unix))]
+fn restore_stdout(_stdout: File) {}
+
+/// Prints a line when a task starts, or is not executed
+fn plain_event(event: &BuildEvent) {
+    match event {
+        BuildEvent::TaskStarted { path } => tracing::info!("> Task {path}"),
+        BuildEvent::TaskFinished { path, outcome, .. } => not_executed(path, outcome),
+        _ => {}
+    }
+}
+
+/// Prints a line for a task that finished without executing
+fn not_executed(path: &str, outcome: &TaskOutcome) {
+    match outcome {
+        TaskOutcome::NotExecuted { dependency } => {
+            tracing::info!("> Task {path} NOT EXECUTED, {dependency} did not complete")
+        }
+        TaskOutcome::Skipped => println!("{path} SKIPPED"),
+        TaskOutcome::Success | TaskOutcome::Failed { .. } => {}
+    }
+}
+
+/// The progress of the build shown in the status area
+#[derive(Debug)]
+struct Progress {
+    start: Instant,
+    total: usize,
+    finished: usize,
+    running: Vec<String>,
+}
+
+/// A console that keeps a status area at the bottom of the terminal.
+///
+/// The lines above the status area are made the scrolling region of the terminal, so that
+/// anything written to the terminal scrolls above the status area.
+#[derive(Debug)]
+struct RichConsole {
+    progress: Mutex<Progress>,
+    /// The last row of the scrolling region, 1-based
+    region_end: u16,
+    width: usize,
+    stopped: AtomicBool,
+}
+
+impl RichConsole {
+    /// Reserves the status area, if stdout is a terminal large enough for it
+    fn start() -> Option<Self> {
+        let (Width(width), Height(rows)) = terminal_size_of(std::io::stdout())?;
+        let height = 1 + RUNNING_LINES as u16;
+        if rows <= height + 1 {
+            return None;
+        }
+        let region_end = rows - height;
+        let mut stdout = std::io::stdout().lock();
+        let reserve = "\n".repeat(height as usize);
+        write!(
+            stdout,
+            "{reserve}\x1b[{height}A\x1b7\x1b[1;{region_end}r\x1b8"
+        )
+        .ok()?;
+        stdout.flush().ok()?;
+        Some(Self {
+            progress: Mutex::new(Progress {
+                start: Instant::now(),
+                total: 0,
+                finished: 0,
+                running: vec![],
+            }),
+            region_end,
+            width: width as usize,
+            stopped: AtomicBool::new(false),
+        })
+    }
+
+    fn on_event(&self, event: &BuildEvent) {
+        {
+            let mut progress = self.progress.lock().unwrap();
+            match event {
+                BuildEvent::TaskGraphReady { tasks } => {
+                    // continuous builds show the progress of each build from the start
+                    progress.start = Instant::now();
+                    progress.total = tasks.len();
+                    progress.finished = 0;
+                }
+                BuildEvent::TaskStarted { path } => progress.running.push(path.clone()),
+                BuildEvent::TaskFinished { path, outcome, .. } => {
+                    progress.running.retain(|running| running != path);
+                    progress.finished += 1;
+                    not_executed(path, outcome);
+                }
+                _ => {}
+            }
+        }
+        self.draw();
+    }
+
+    /// Renders the lines of the status area
+    fn lines(&self) -> Vec<String> {
+        let progress = self.progress.lock().unwrap();
+        let percent = match progress.total {
+            0 => 0,
+            total => progress.finished * 100 / total,
+        };
+        let done = BAR_WIDTH * percent / 100;
+        let mut lines = vec![format!(
+            "<{}{}> {percent}% EXECUTING [{}s]",
+            "=".repeat(done),
+            "-".repeat(BAR_WIDTH - done),
+            progress.start.elapsed().as_secs()
+        )];
+        for i in 0..RUNNING_LINES {
+            lines.push(match progress.running.get(i) {
+                Some(path) => format!("> {path}"),
+                None => "> IDLE".to_string(),
+            });
+        }
+        lines
+    }
+
+    /// Redraws the status area, keeping the cursor where it is
+    fn draw(&self) {
+        let mut output = String::from("\x1b7");
+        for (i, line) in self.lines().into_iter().enumerate() {
+            let row = self.region_end as usize + 1 + i;
+            let line: String = line.chars().take(self.width).collect();
+            output.push_str(&format!("\x1b[{row};1H\x1b[2K{line}"));
+        }
+        output.push_str("\x1b8");
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(output.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    /// Clears the status area and restores the scrolling region of the whole terminal
+    fn clear(&self) {
+        let mut output = String::from("\x1b7");
+        for i in 0..=RUNNING_LINES {
+            let row = self.region_end as usize + 1 + i;
+            output.push_str(&format!("\x1b[{row};1H\x1b[2K"));
+        }
+        output.push_str("\x1b[r\x1b8");
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(output.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_lines() {
+        let console = RichConsole {
+            progress: Mutex::new(Progress {
+                start: Instant::now(),
+                total: 0,
+                finished: 0,
+                running: vec![],
+            }),
+            region_end: 20,
+            width: 80,
+            stopped: AtomicBool::new(false),
+        };
+        console.progress.lock().unwrap().total = 4;
+        console.progress.lock().unwrap().finished = 2;
+        console
+            .progress
+            .lock()
+            .unwrap()
+            .running
+            .push(":build".to_string());
+        assert_eq!(
+            console.lines(),
+            ["<======-------> 50% EXECUTING [0s]", "> :build"]
+        );
+    }
+}
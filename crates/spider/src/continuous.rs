@@ -0,0 +1,155 @@
+//! Continuous builds, which rerun the affected tasks whenever the files of the build change
+
+use crate::{build_outcome, create_spider};
+use notify::{EventKind, RecursiveMode, Watcher};
+use spider_core::error::{ErrorKind, Result};
+use spider_core::events::BuildListeners;
+use spider_core::execution::graph::TaskGraph;
+use spider_core::fs::watch::FileWatchSet;
+use spider_core::invocation::start_parameter::StartParameter;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long to wait for more changes after a change, before rerunning the build
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Runs builds until interrupted with Ctrl-C.
+///
+/// After each build, the scripts of the build and the inputs of its tasks are watched. A change
+/// to a script reruns the whole build, and a change to an input reruns only the tasks affected by
+/// it, excluding all others. The tasks excluded from a rerun are still watched, as are the tasks
+/// of the build as requested.
+pub async fn run(parameters: &StartParameter, listeners: BuildListeners) -> Result<()> {
+    let mut current = parameters.clone();
+    loop {
+        let mut spider = create_spider(parameters, listeners.clone())?;
+        let graph = match spider.load_for(parameters).await {
+            Ok(()) => spider.task_graph(parameters).await.ok(),
+            Err(_) => None,
+        };
+        let watched = match &graph {
+            Some(graph) => spider.watched_paths(graph).await,
+            None => spider.script_paths().into_iter().collect(),
+        };
+        let _ = build_outcome(spider.run(&current).await);
+
+        eprintln!("\nWaiting for changes to input files... (ctrl-c to exit)");
+        let changed = tokio::select! {
+            changed = wait_for_changes(&watched) => changed.map_err(ErrorKind::custom)?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        for path in &changed {
+            eprintln!("modified: {}", path.display());
+        }
+
+        let scripts = spider.script_paths().into_iter().collect::<FileWatchSet>();
+        current = match graph.filter(|_| !changed.iter().any(|c| scripts.matches(c))) {
+            Some(graph) => rerun_parameters(parameters, &graph, spider.root_dir(), &changed).await,
+            None => parameters.clone(),
+        };
+    }
+}
+
+/// Gets the parameters of a rerun after `changed` files changed, excluding the tasks of `graph`
+/// that aren't affected by the changes
+async fn rerun_parameters(
+    parameters: &StartParameter,
+    graph: &TaskGraph,
+    root_dir: &Path,
+    changed: &[PathBuf],
+) -> StartParameter {
+    let affected = graph.affected_by(root_dir, changed).await;
+    let mut excluded = parameters.excluded_task_names().to_vec();
+    excluded.extend(
+        graph
+            .paths()
+            .filter(|path| !affected.iter().any(|a| a == path))
+            .map(str::to_string),
+    );
+    let mut rerun = parameters.clone();
+    rerun.set_excluded_task_names(excluded);
+    rerun
+}
+
+/// Waits until some of the watched files change, returning the changed files once no more
+/// changes happened for [`DEBOUNCE`]
+async fn wait_for_changes(watched: &FileWatchSet) -> notify::Result<Vec<PathBuf>> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = sender.send(event);
+        }
+    })?;
+    // files are watched through their directory, so that they may be created or replaced
+    let mut directories = BTreeMap::new();
+    for path in watched.paths() {
+        if path.is_dir() {
+            directories.insert(path.to_path_buf(), RecursiveMode::Recursive);
+        } else if let Some(parent) = path.parent().filter(|parent| parent.is_dir()) {
+            directories
+                .entry(parent.to_path_buf())
+                .or_insert(RecursiveMode::NonRecursive);
+        }
+    }
+    for (directory, mode) in directories {
+        watcher.watch(&directory, mode)?;
+    }
+
+    let mut changed: Vec<PathBuf> = vec![];
+    loop {
+        let event = if changed.is_empty() {
+            receiver.recv().await
+        } else {
+            match tokio::time::timeout(DEBOUNCE, receiver.recv()).await {
+                Ok(event) => event,
+                Err(_) => break,
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+        for path in event.paths {
+            if watched.matches(&path) && !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spider_core::task::Task;
+    use std::collections::{HashMap, HashSet};
+
+    #[tokio::test]
+    async fn test_rerun_excludes_unaffected_tasks() {
+        let compile = Task::new(":compile");
+        compile.add_input("src").await.unwrap();
+        let docs = Task::new(":docs");
+        docs.add_input("README.md").await.unwrap();
+        let available = HashMap::from([
+            (":compile".to_string(), compile.clone()),
+            (":docs".to_string(), docs.clone()),
+        ]);
+        let mut parameters = StartParameter::new();
+        parameters.set_task_names([":compile", ":docs"]);
+        let graph = TaskGraph::new(&[compile, docs], &available, &HashSet::new())
+            .await
+            .unwrap();
+        let root = Path::new("/build");
+
+        let rerun = rerun_parameters(&parameters, &graph, root, &[root.join("src/lib.rs")]).await;
+        assert_eq!(rerun.excluded_task_names(), [":docs"]);
+        // the graph of the requested tasks still covers the excluded task, so that a later change
+        // to its inputs reruns it
+        let rerun = rerun_parameters(&parameters, &graph, root, &[root.join("README.md")]).await;
+        assert_eq!(rerun.excluded_task_names(), [":compile"]);
+    }
+}
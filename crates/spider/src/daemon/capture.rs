@@ -0,0 +1,112 @@
+//! Captures what a build writes to the standard output and error of the daemon
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::thread::JoinHandle;
+
+/// The stream some output was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn fd(self) -> RawFd {
+        match self {
+            Stream::Stdout => libc::STDOUT_FILENO,
+            Stream::Stderr => libc::STDERR_FILENO,
+        }
+    }
+}
+
+/// Redirects the standard output and error of this process into pipes while it exists.
+///
+/// Redirection affects the whole process, so only one capture may exist at a time.
+pub struct OutputCapture {
+    saved: Vec<(Stream, OwnedFd)>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl OutputCapture {
+    /// Starts capturing, passing everything written to `on_output` as it is read
+    pub fn start<F>(on_output: F) -> io::Result<Self>
+    where
+        F: Fn(Stream, String) + Clone + Send + 'static,
+    {
+        let mut capture = Self {
+            saved: vec![],
+            readers: vec![],
+        };
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            flush(stream);
+            let (read, write) = pipe()?;
+            let saved = cvt(unsafe { libc::dup(stream.fd()) })?;
+            // SAFETY: dup returned a new descriptor that nothing else owns
+            capture
+                .saved
+                .push((stream, unsafe { OwnedFd::from_raw_fd(saved) }));
+            cvt(unsafe { libc::dup2(write.as_raw_fd(), stream.fd()) })?;
+            drop(write);
+
+            let on_output = on_output.clone();
+            capture.readers.push(std::thread::spawn(move || {
+                let mut read = File::from(read);
+                let mut buffer = [0; 8192];
+                while let Ok(read) = read.read(&mut buffer) {
+                    if read == 0 {
+                        break;
+                    }
+                    on_output(
+                        stream,
+                        String::from_utf8_lossy(&buffer[..read]).into_owned(),
+                    );
+                }
+            }));
+        }
+        Ok(capture)
+    }
+
+    /// Stops capturing, returning once all captured output was passed on
+    pub fn finish(self) {
+        drop(self);
+    }
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        for (stream, saved) in self.saved.drain(..) {
+            flush(stream);
+            // restoring the stream closes the last write end of its pipe, ending its reader
+            unsafe { libc::dup2(saved.as_raw_fd(), stream.fd()) };
+        }
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+    }
+}
+
+fn flush(stream: Stream) {
+    let _ = match stream {
+        Stream::Stdout => io::stdout().flush(),
+        Stream::Stderr => io::stderr().flush(),
+    };
+}
+
+/// Creates a pipe, returning its read and write ends
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    cvt(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+    // SAFETY: pipe returned two new descriptors that nothing else owns
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// Converts the result of a libc call to an io result
+fn cvt(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
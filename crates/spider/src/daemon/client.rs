@@ -0,0 +1,279 @@
+//! The client side of the protocol, used by the `spider` binary
+
+use crate::BuildFailure;
+use crate::daemon::capture::Stream;
+use crate::daemon::protocol::{Request, Response, read_message, write_message};
+use crate::daemon::socket_path;
+use crate::events::from_json;
+use spider_core::error::{ErrorKind, Result};
+use spider_core::events::{BuildEvent, BuildListener};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::BufReader;
+use tokio::net::UnixStream;
+
+/// The hidden option that runs the daemon itself
+pub const DAEMON_SERVER_OPTION: &str = "--daemon-server";
+/// How long to wait for a new daemon to accept connections
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a build in the daemon, starting one if none is running.
+///
+/// The build runs in the working directory and with the environment variables of this process.
+/// Output of the build is written to the standard output and error of this process, and events
+/// are passed to `listener`.
+pub async fn run_build<L: BuildListener>(
+    args: Vec<String>,
+    listener: L,
+) -> std::result::Result<(), BuildFailure> {
+    match run_in_daemon(args, listener).await {
+        Ok(None) => Ok(()),
+        Ok(Some(report)) => Err(BuildFailure::Daemon { report }),
+        Err(e) => Err(BuildFailure::Local(e)),
+    }
+}
+
+/// Runs a build in the daemon, returning the report of its failure if it failed
+async fn run_in_daemon<L: BuildListener>(args: Vec<String>, listener: L) -> Result<Option<String>> {
+    let socket = socket()?;
+    let stream = match UnixStream::connect(&socket).await {
+        Ok(stream) => stream,
+        Err(_) => start_daemon(&socket).await?,
+    };
+    let request = Request::Build {
+        cwd: std::env::current_dir()?,
+        args,
+        env: client_env(),
+    };
+    send_build(stream, &request, &listener, |stream, text| match stream {
+        Stream::Stdout => {
+            print!("{text}");
+            io::stdout().flush()
+        }
+        Stream::Stderr => {
+            eprint!("{text}");
+            Ok(())
+        }
+    })
+    .await
+}
+
+/// Gets the environment variables of this process, leaving out those that aren't unicode
+fn client_env() -> BTreeMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// Sends a build request to the daemon, passing the output of the build to `output` and its
+/// events to `listener` until it finishes. Returns the report of the failure if the build failed.
+async fn send_build<L, O>(
+    stream: UnixStream,
+    request: &Request,
+    listener: &L,
+    mut output: O,
+) -> Result<Option<String>>
+where
+    L: BuildListener,
+    O: FnMut(Stream, &str) -> io::Result<()>,
+{
+    let (reader, mut writer) = stream.into_split();
+    write_message(&mut writer, request).await?;
+
+    let mut reader = BufReader::new(reader);
+    while let Some(response) = read_message(&mut reader).await? {
+        match response {
+            Response::Stdout { text } => output(Stream::Stdout, &text)?,
+            Response::Stderr { text } => output(Stream::Stderr, &text)?,
+            Response::Event { event } => {
+                if let Some(event) = from_json(&event) {
+                    listener.on_event(&event);
+                }
+            }
+            Response::BuildFinished { error, report } => {
+                listener.on_event(&BuildEvent::BuildFinished {
+                    failure: error.clone(),
+                });
+                return Ok(error.map(|message| report.unwrap_or(message)));
+            }
+            Response::Error { message } => return Err(ErrorKind::custom(message).into()),
+            other => {
+                return Err(
+                    ErrorKind::custom(format!("unexpected daemon response {other:?}")).into(),
+                );
+            }
+        }
+    }
+    Err(ErrorKind::custom("the daemon disappeared before the build finished").into())
+}
+
+/// Prints the state of the running daemon
+pub async fn status() -> Result<()> {
+    match request(&socket()?, Request::Status).await? {
+        Some(Response::Status {
+            pid,
+            spider_version,
+            busy,
+            uptime_secs,
+            idle_secs,
+            builds,
+            warm_builds,
+            cached_scripts,
+        }) => {
+            println!("   PID STATUS   VERSION  UPTIME  IDLE  BUILDS  WARM  SCRIPTS");
+            let status = if busy { "BUSY" } else { "IDLE" };
+            println!(
+                "{pid:>6} {status:<8} {spider_version:<8} {uptime_secs:>5}s {idle_secs:>4}s \
+                 {builds:>7} {warm_builds:>5} {cached_scripts:>8}"
+            );
+        }
+        Some(other) => {
+            return Err(ErrorKind::custom(format!("unexpected daemon response {other:?}")).into());
+        }
+        None => println!("No spider daemon is running."),
+    }
+    Ok(())
+}
+
+/// Stops the running daemon
+pub async fn stop() -> Result<()> {
+    match request(&socket()?, Request::Stop).await? {
+        Some(_) => println!("Spider daemon stopped."),
+        None => println!("No spider daemon is running."),
+    }
+    Ok(())
+}
+
+/// Sends a request to the daemon listening on `socket`, returning its single response
+async fn request(socket: &Path, request: Request) -> Result<Option<Response>> {
+    let Ok(stream) = UnixStream::connect(socket).await else {
+        return Ok(None);
+    };
+    let (reader, mut writer) = stream.into_split();
+    write_message(&mut writer, &request).await?;
+    Ok(read_message(&mut BufReader::new(reader)).await?)
+}
+
+fn socket() -> std::result::Result<PathBuf, ErrorKind> {
+    socket_path().ok_or_else(|| {
+        ErrorKind::custom("could not find the spider user home for the daemon socket")
+    })
+}
+
+/// Starts a daemon in the background, and connects to it once it listens
+async fn start_daemon(socket: &Path) -> Result<UnixStream> {
+    tracing::info!("Starting a spider daemon");
+    Command::new(std::env::current_exe()?)
+        .arg(DAEMON_SERVER_OPTION)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+
+    let start = Instant::now();
+    loop {
+        match UnixStream::connect(socket).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) if start.elapsed() > STARTUP_TIMEOUT => {
+                return Err(ErrorKind::custom(format!(
+                    "the spider daemon did not start listening on {socket:?}: {e}"
+                ))
+                .into());
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::server;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    /// Runs a build in the daemon listening on `socket`, returning its events
+    async fn build_in_daemon(socket: &Path, build_dir: &Path, args: &[&str]) -> Vec<BuildEvent> {
+        let events = Arc::new(Mutex::new(vec![]));
+        let listener = {
+            let events = events.clone();
+            move |event: &BuildEvent| events.lock().unwrap().push(event.clone())
+        };
+        let request = Request::Build {
+            cwd: build_dir.to_path_buf(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: client_env(),
+        };
+        let stream = UnixStream::connect(socket).await.unwrap();
+        let failure = send_build(stream, &request, &listener, |_, _| Ok(()))
+            .await
+            .unwrap();
+        assert_eq!(failure, None, "build should pass");
+        events.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_build_in_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_dir = dir.path().join("build");
+        std::fs::create_dir_all(build_dir.join("app")).unwrap();
+        let settings = build_dir.join("settings.spider.rs");
+        std::fs::write(&settings, "include(\":app\");\n").unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let server = tokio::spawn({
+            let socket = socket.clone();
+            async move { server::serve(&socket, Duration::from_secs(60)).await }
+        });
+        while UnixStream::connect(&socket).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let user_home = dir.path().join("home");
+        let user_home = user_home.to_str().unwrap();
+        let args = ["spider", "--user-home", user_home, "app:tasks"];
+        let configured = |events: &[BuildEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, BuildEvent::ProjectConfigured { .. }))
+        };
+        let events = build_in_daemon(&socket, &build_dir, &args).await;
+        assert!(configured(&events));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BuildEvent::TaskGraphReady { tasks } if tasks == &[":app:tasks"]
+        )));
+        assert!(matches!(
+            events.last(),
+            Some(BuildEvent::BuildFinished { failure: None })
+        ));
+
+        // the second build reuses the loaded build, until a script changes
+        let events = build_in_daemon(&socket, &build_dir, &args).await;
+        assert!(!configured(&events));
+        std::fs::File::options()
+            .append(true)
+            .open(&settings)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60 * 60))
+            .unwrap();
+        let events = build_in_daemon(&socket, &build_dir, &args).await;
+        assert!(configured(&events));
+
+        let Some(Response::Status {
+            builds,
+            warm_builds,
+            ..
+        }) = request(&socket, Request::Status).await.unwrap()
+        else {
+            panic!("the daemon should report its status")
+        };
+        assert_eq!((builds, warm_builds), (3, 1));
+        request(&socket, Request::Stop).await.unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
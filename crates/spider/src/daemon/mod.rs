@@ -0,0 +1,53 @@
+//! An optional long-lived daemon running builds for thin `spider` clients.
+//!
+//! Builds run in the daemon when enabled with `--daemon` or `SPIDER_DAEMON=true`. The daemon
+//! listens on a Unix domain socket in the spider user home, and keeps the builds it ran loaded
+//! until their scripts or properties files change. Clients send a single request per connection
+//! and read responses until the request completes, using the protocol in [`protocol`].
+
+pub mod capture;
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+use crate::cli::Args;
+use protocol::PROTOCOL_VERSION;
+use spider_core::fs::user_home_dir;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The environment variable running builds in the daemon when `true`
+pub const DAEMON_ENV: &str = "SPIDER_DAEMON";
+/// The environment variable setting how many seconds an idle daemon waits before stopping
+pub const IDLE_TIMEOUT_ENV: &str = "SPIDER_DAEMON_IDLE_TIMEOUT";
+/// How long an idle daemon waits for a build before stopping
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(3 * 60 * 60);
+
+/// Gets the socket of the daemon compatible with this version of spider.
+///
+/// Daemons of other versions of spider, or speaking another protocol version, use another
+/// socket, so that they never receive requests they don't understand.
+pub fn socket_path() -> Option<PathBuf> {
+    Some(
+        user_home_dir()?
+            .join("daemon")
+            .join(env!("CARGO_PKG_VERSION"))
+            .join(format!("daemon-v{PROTOCOL_VERSION}.sock")),
+    )
+}
+
+/// Gets the idle timeout of daemons started by this process
+pub fn idle_timeout() -> Duration {
+    std::env::var(IDLE_TIMEOUT_ENV)
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_IDLE_TIMEOUT)
+}
+
+/// Whether builds run in the daemon: only when enabled with `--daemon` or [`DAEMON_ENV`], unless
+/// disabled with `--no-daemon`. Profiled builds always run in the client.
+pub fn enabled(args: &Args) -> bool {
+    let requested = args.daemon || std::env::var(DAEMON_ENV).is_ok_and(|value| value == "true");
+    requested && !args.no_daemon && args.profile.is_none()
+}
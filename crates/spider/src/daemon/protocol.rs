@@ -0,0 +1,153 @@
+//! The request/response protocol between `spider` clients and the daemon.
+//!
+//! Every message is a JSON object on its own line, carrying the protocol version it was written
+//! with. A client writes one [`Request`], and the daemon answers with [`Response`]s until the
+//! request is complete.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The version of the protocol. Must be increased on any incompatible change to the messages.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// A request from a client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Request {
+    /// Runs a build with the given command line, from the given directory and with the given
+    /// environment variables
+    Build {
+        cwd: PathBuf,
+        args: Vec<String>,
+        env: BTreeMap<String, String>,
+    },
+    /// Reports the state of the daemon
+    Status,
+    /// Stops the daemon once its current build finished
+    Stop,
+}
+
+/// A response from the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Response {
+    /// Text the build wrote to its standard output
+    Stdout { text: String },
+    /// Text the build wrote to its standard error
+    Stderr { text: String },
+    /// A build event, in the format of `--events`
+    Event { event: serde_json::Value },
+    /// The build finished, with its error message and the error as displayed by the daemon if
+    /// it failed
+    BuildFinished {
+        error: Option<String>,
+        report: Option<String>,
+    },
+    /// The state of the daemon
+    Status {
+        pid: u32,
+        spider_version: String,
+        busy: bool,
+        uptime_secs: u64,
+        idle_secs: u64,
+        builds: u64,
+        warm_builds: usize,
+        cached_scripts: usize,
+    },
+    /// The daemon is stopping
+    Stopping,
+    /// The request could not be handled
+    Error { message: String },
+}
+
+/// A message together with the protocol version it was written with
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    message: T,
+}
+
+/// Writes a message as a single line
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        message,
+    };
+    let mut line = serde_json::to_string(&envelope)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Reads the next message, or `None` once the other side closed the connection
+pub async fn read_message<R, T>(reader: &mut R) -> io::Result<Option<T>>
+where
+    R: AsyncBufRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    let envelope: Envelope<serde_json::Value> = serde_json::from_str(&line)?;
+    if envelope.version != PROTOCOL_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "unsupported daemon protocol version {}, expected {PROTOCOL_VERSION}",
+                envelope.version
+            ),
+        ));
+    }
+    Ok(Some(serde_json::from_value(envelope.message)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let request = Request::Build {
+            cwd: PathBuf::from("/project"),
+            args: vec!["spider".to_string(), "build".to_string()],
+            env: BTreeMap::from([("HOME".to_string(), "/home/user".to_string())]),
+        };
+        let mut buffer = vec![];
+        write_message(&mut buffer, &request).await.unwrap();
+        let line = String::from_utf8(buffer.clone()).unwrap();
+        assert!(line.starts_with(r#"{"version":3,"type":"build","#));
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        let read: Option<Request> = read_message(&mut reader).await.unwrap();
+        assert_eq!(read, Some(request));
+        let end: Option<Request> = read_message(&mut reader).await.unwrap();
+        assert_eq!(end, None);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_other_versions() {
+        let line = b"{\"version\":0,\"type\":\"status\"}\n";
+        let mut reader = BufReader::new(&line[..]);
+        let error = read_message::<_, Request>(&mut reader).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,366 @@
+//! The daemon side of the protocol
+
+use crate::cli::Args;
+use crate::daemon::capture::{OutputCapture, Stream};
+use crate::daemon::protocol::{Request, Response, read_message, write_message};
+use crate::events::to_json;
+use crate::logging;
+use spider_core::error::{Result, set_show_stacktrace};
+use spider_core::events::BuildEvent;
+use spider_core::invocation::script::ScriptCache;
+use spider_core::invocation::spider::Spider;
+use spider_core::invocation::start_parameter::StartParameter;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::BufReader;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{Mutex, Notify, mpsc};
+
+/// The state a daemon keeps between builds
+struct DaemonState {
+    started: Instant,
+    last_active: StdMutex<Instant>,
+    builds: AtomicU64,
+    warm_builds: AtomicUsize,
+    /// Held while a build runs, as builds redirect the output and change the environment of the
+    /// whole process. Holds the builds kept loaded, by their root directory.
+    build_lock: Mutex<HashMap<PathBuf, WarmBuild>>,
+    script_cache: ScriptCache,
+    stop: Notify,
+}
+
+impl DaemonState {
+    fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    fn idle(&self) -> Duration {
+        self.last_active.lock().unwrap().elapsed()
+    }
+
+    /// Waits until no build ran or was requested for `idle_timeout`
+    async fn idle_for(&self, idle_timeout: Duration) {
+        loop {
+            let remaining = idle_timeout.saturating_sub(self.idle());
+            if !remaining.is_zero() {
+                tokio::time::sleep(remaining).await;
+                continue;
+            }
+            // a running build touches the state before it releases the lock, so wait for it
+            // instead of polling
+            let builds = self.build_lock.lock().await;
+            if self.idle() >= idle_timeout {
+                drop(builds);
+                return;
+            }
+        }
+    }
+}
+
+/// Serves requests on `socket` until stopped, or idle for `idle_timeout`
+pub async fn serve(socket: &Path, idle_timeout: Duration) -> io::Result<()> {
+    let listener = bind(socket).await?;
+    let state = Arc::new(DaemonState {
+        started: Instant::now(),
+        last_active: StdMutex::new(Instant::now()),
+        builds: AtomicU64::new(0),
+        warm_builds: AtomicUsize::new(0),
+        build_lock: Mutex::new(HashMap::new()),
+        script_cache: ScriptCache::new(),
+        stop: Notify::new(),
+    });
+    tracing::debug!("daemon listening on {socket:?}");
+    let idle = state.idle_for(idle_timeout);
+    tokio::pin!(idle);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, &state).await {
+                        tracing::warn!("daemon connection failed: {e}");
+                    }
+                });
+            }
+            _ = &mut idle => {
+                tracing::debug!("daemon idle for {idle_timeout:?}, stopping");
+                break;
+            }
+            _ = state.stop.notified() => break,
+        }
+    }
+    // let a running build finish before going away
+    let _builds = state.build_lock.lock().await;
+    let _ = std::fs::remove_file(socket);
+    Ok(())
+}
+
+/// Binds the socket, replacing the socket of a daemon that no longer runs
+async fn bind(socket: &Path) -> io::Result<UnixListener> {
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::exists(socket)? {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a daemon is already listening on {socket:?}"),
+            ));
+        }
+        std::fs::remove_file(socket)?;
+    }
+    UnixListener::bind(socket)
+}
+
+/// Handles the single request of a connection
+async fn handle(stream: UnixStream, state: &DaemonState) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let Some(request) = read_message::<_, Request>(&mut BufReader::new(reader)).await? else {
+        return Ok(());
+    };
+    state.touch();
+    match request {
+        Request::Status => {
+            let status = Response::Status {
+                pid: std::process::id(),
+                spider_version: env!("CARGO_PKG_VERSION").to_string(),
+                busy: state.build_lock.try_lock().is_err(),
+                uptime_secs: state.started.elapsed().as_secs(),
+                idle_secs: state.idle().as_secs(),
+                builds: state.builds.load(Ordering::Relaxed),
+                warm_builds: state.warm_builds.load(Ordering::Relaxed),
+                cached_scripts: state.script_cache.len().await,
+            };
+            write_message(&mut writer, &status).await?;
+        }
+        Request::Stop => {
+            write_message(&mut writer, &Response::Stopping).await?;
+            state.stop.notify_one();
+        }
+        Request::Build { cwd, args, env } => {
+            let (sender, mut receiver) = mpsc::unbounded_channel();
+            let forward = tokio::spawn(async move {
+                while let Some(response) = receiver.recv().await {
+                    write_message(&mut writer, &response).await?;
+                }
+                io::Result::Ok(())
+            });
+
+            let result = {
+                let mut builds = state.build_lock.lock().await;
+                state.builds.fetch_add(1, Ordering::Relaxed);
+                let output = sender.clone();
+                let capture = OutputCapture::start(move |stream, text| {
+                    let _ = output.send(match stream {
+                        Stream::Stdout => Response::Stdout { text },
+                        Stream::Stderr => Response::Stderr { text },
+                    });
+                })?;
+                let result = match ClientEnvironment::apply(&cwd, &env) {
+                    Ok(_environment) => {
+                        build(args, &mut builds, &state.script_cache, sender.clone()).await
+                    }
+                    Err(e) => Err(e.into()),
+                };
+                capture.finish();
+                state.warm_builds.store(builds.len(), Ordering::Relaxed);
+                state.touch();
+                result
+            };
+            let _ = sender.send(Response::BuildFinished {
+                error: result.as_ref().err().map(|e| e.kind.to_string()),
+                report: result.err().map(|e| e.to_string()),
+            });
+            drop(sender);
+            forward.await??;
+        }
+    }
+    Ok(())
+}
+
+/// Runs a build for a client, forwarding its events. The build is reused from `builds` if it was
+/// loaded before with the same parameters and its scripts didn't change since.
+async fn build(
+    args: Vec<String>,
+    builds: &mut HashMap<PathBuf, WarmBuild>,
+    script_cache: &ScriptCache,
+    sender: mpsc::UnboundedSender<Response>,
+) -> Result<()> {
+    let args = Args::parse_args(args);
+    logging::set_level(args.log_level());
+    set_show_stacktrace(args.show_stacktrace());
+    let parameters = args.start_parameter();
+    let spider = Spider::with_start_parameter(&parameters)?;
+    let root_dir = spider.root_dir().to_path_buf();
+    let key = ModelKey::new(&spider, &parameters);
+    let mut warm = match builds.remove(&root_dir) {
+        Some(warm) if warm.key == key && warm.is_current() => {
+            tracing::debug!("reusing the loaded build in {root_dir:?}");
+            warm
+        }
+        _ => WarmBuild::new(spider, key, script_cache),
+    };
+
+    *warm.client.lock().unwrap() = Some(sender);
+    let started = SystemTime::now();
+    let result = warm.spider.run(&parameters).await;
+    *warm.client.lock().unwrap() = None;
+
+    warm.scripts = warm
+        .spider
+        .script_paths()
+        .into_iter()
+        .map(|path| {
+            let modified = modified(&path);
+            (path, modified)
+        })
+        .collect();
+    // a script changed while the build ran may not be part of what was loaded
+    let changed_while_running = warm
+        .scripts
+        .iter()
+        .any(|(_, modified)| modified.is_some_and(|modified| modified >= started));
+    if !changed_while_running {
+        builds.insert(root_dir, warm);
+    }
+    result
+}
+
+/// A build kept loaded between requests
+struct WarmBuild {
+    spider: Spider,
+    key: ModelKey,
+    /// The scripts and properties files of the build, with their modification times
+    scripts: Vec<(PathBuf, Option<SystemTime>)>,
+    /// Where the events of the build go, set while it runs for a client
+    client: Arc<StdMutex<Option<mpsc::UnboundedSender<Response>>>>,
+}
+
+impl WarmBuild {
+    fn new(mut spider: Spider, key: ModelKey, script_cache: &ScriptCache) -> Self {
+        spider.set_script_cache(script_cache.clone());
+        let client: Arc<StdMutex<Option<mpsc::UnboundedSender<Response>>>> = Arc::default();
+        let events = client.clone();
+        spider.add_listener(move |event: &BuildEvent| {
+            // the client reports the end of the build once all output was forwarded
+            if matches!(event, BuildEvent::BuildFinished { .. }) {
+                return;
+            }
+            if let Some(sender) = &*events.lock().unwrap() {
+                let _ = sender.send(Response::Event {
+                    event: to_json(event),
+                });
+            }
+        });
+        Self {
+            spider,
+            key,
+            scripts: vec![],
+            client,
+        }
+    }
+
+    /// Checks that none of the scripts of this build changed since it was loaded
+    fn is_current(&self) -> bool {
+        self.scripts
+            .iter()
+            .all(|(path, loaded)| modified(path) == *loaded)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// What a build was loaded with, besides its scripts
+#[derive(Debug, PartialEq)]
+struct ModelKey {
+    current_dir: PathBuf,
+    user_home: Option<PathBuf>,
+    settings_file: Option<PathBuf>,
+    init_scripts: Vec<PathBuf>,
+    project_properties: BTreeMap<String, String>,
+    system_properties: BTreeMap<String, String>,
+    configure_on_demand: bool,
+}
+
+impl ModelKey {
+    fn new(spider: &Spider, parameters: &StartParameter) -> Self {
+        let cwd = spider.cwd();
+        let sorted = |properties: &HashMap<String, String>| {
+            properties
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        };
+        Self {
+            current_dir: spider.details().layout().current_dir().to_path_buf(),
+            user_home: spider.user_home().map(Path::to_path_buf),
+            settings_file: parameters.settings_file().map(|file| cwd.join(file)),
+            init_scripts: parameters
+                .init_scripts()
+                .iter()
+                .map(|script| cwd.join(script))
+                .collect(),
+            project_properties: sorted(parameters.project_properties()),
+            system_properties: sorted(parameters.system_properties()),
+            configure_on_demand: parameters.is_configure_on_demand(),
+        }
+    }
+}
+
+/// The working directory and environment variables of a client, applied to the daemon process
+/// while it runs a build for the client and restored once dropped.
+///
+/// Builds hold the build lock while they run, and nothing else in the daemon reads the
+/// environment, so changing it doesn't race with other threads.
+struct ClientEnvironment {
+    saved_dir: PathBuf,
+    saved_vars: Vec<(OsString, Option<OsString>)>,
+}
+
+impl ClientEnvironment {
+    fn apply(cwd: &Path, env: &BTreeMap<String, String>) -> io::Result<Self> {
+        let saved_dir = std::env::current_dir()?;
+        std::env::set_current_dir(cwd)?;
+        let mut saved_vars = vec![];
+        for (key, value) in std::env::vars_os() {
+            if !key.to_str().is_some_and(|key| env.contains_key(key)) {
+                // SAFETY: see the type documentation
+                unsafe { std::env::remove_var(&key) };
+                saved_vars.push((key, Some(value)));
+            }
+        }
+        for (key, value) in env {
+            let current = std::env::var_os(key);
+            if current.as_deref() != Some(value.as_ref()) {
+                // SAFETY: see the type documentation
+                unsafe { std::env::set_var(key, value) };
+                saved_vars.push((key.into(), current));
+            }
+        }
+        Ok(Self {
+            saved_dir,
+            saved_vars,
+        })
+    }
+}
+
+impl Drop for ClientEnvironment {
+    fn drop(&mut self) {
+        for (key, value) in self.saved_vars.drain(..).rev() {
+            // SAFETY: see the type documentation
+            match value {
+                Some(value) => unsafe { std::env::set_var(key, value) },
+                None => unsafe { std::env::remove_var(key) },
+            }
+        }
+        let _ = std::env::set_current_dir(&self.saved_dir);
+    }
+}
@@ -0,0 +1,171 @@
+//! Writes the events of a build as newline-delimited JSON
+
+use serde_json::{Value, json};
+use spider_core::events::{BuildEvent, BuildListener, TaskOutcome};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A listener writing one JSON object per line for every build event
+pub struct JsonEventWriter<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonEventWriter<W> {
+    /// Creates a new event writer
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl<W: Write + Send> BuildListener for JsonEventWriter<W> {
+    fn on_event(&self, event: &BuildEvent) {
+        let mut writer = self.writer.lock().unwrap();
+        let written = writeln!(writer, "{}", to_json(event)).and_then(|()| writer.flush());
+        if let Err(e) = written {
+            tracing::warn!("could not write build event: {e}");
+        }
+    }
+}
+
+/// Converts an event to its JSON representation
+pub fn to_json(event: &BuildEvent) -> Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let mut value = match event {
+        BuildEvent::SettingsEvaluated {
+            root_dir,
+            settings_script,
+        } => json!({
+            "event": "settingsEvaluated",
+            "rootDir": root_dir,
+            "settingsScript": settings_script,
+        }),
+        BuildEvent::ProjectConfigured { path } => json!({
+            "event": "projectConfigured",
+            "path": path,
+        }),
+        BuildEvent::TaskGraphReady { tasks } => json!({
+            "event": "taskGraphReady",
+            "tasks": tasks,
+        }),
+        BuildEvent::TaskStarted { path } => json!({
+            "event": "taskStarted",
+            "path": path,
+        }),
+        BuildEvent::TaskFinished {
+            path,
+            outcome,
+            duration,
+        } => {
+            let mut value = json!({
+                "event": "taskFinished",
+                "path": path,
+                "durationMs": duration.as_millis() as u64,
+            });
+            let outcome = match outcome {
+                TaskOutcome::Success => json!({ "outcome": "success" }),
+                TaskOutcome::Failed { message } => json!({
+                    "outcome": "failed",
+                    "message": message,
+                }),
+                TaskOutcome::Skipped => json!({ "outcome": "skipped" }),
+                TaskOutcome::NotExecuted { dependency } => json!({
+                    "outcome": "notExecuted",
+                    "dependency": dependency,
+                }),
+            };
+            merge(&mut value, outcome);
+            value
+        }
+        BuildEvent::BuildFinished { failure } => json!({
+            "event": "buildFinished",
+            "success": failure.is_none(),
+            "error": failure,
+        }),
+    };
+    merge(&mut value, json!({ "timestamp": timestamp }));
+    value
+}
+
+/// Converts the JSON representation of an event back to the event, ignoring its timestamp
+pub fn from_json(value: &Value) -> Option<BuildEvent> {
+    let string = |key: &str| value[key].as_str().map(str::to_string);
+    let event = match value["event"].as_str()? {
+        "settingsEvaluated" => BuildEvent::SettingsEvaluated {
+            root_dir: string("rootDir")?.into(),
+            settings_script: string("settingsScript").map(PathBuf::from),
+        },
+        "projectConfigured" => BuildEvent::ProjectConfigured {
+            path: string("path")?,
+        },
+        "taskGraphReady" => BuildEvent::TaskGraphReady {
+            tasks: value["tasks"]
+                .as_array()?
+                .iter()
+                .filter_map(|task| task.as_str().map(str::to_string))
+                .collect(),
+        },
+        "taskStarted" => BuildEvent::TaskStarted {
+            path: string("path")?,
+        },
+        "taskFinished" => BuildEvent::TaskFinished {
+            path: string("path")?,
+            outcome: match value["outcome"].as_str()? {
+                "success" => TaskOutcome::Success,
+                "failed" => TaskOutcome::Failed {
+                    message: string("message")?,
+                },
+                "notExecuted" => TaskOutcome::NotExecuted {
+                    dependency: string("dependency")?,
+                },
+                "skipped" => TaskOutcome::Skipped,
+                _ => return None,
+            },
+            duration: Duration::from_millis(value["durationMs"].as_u64()?),
+        },
+        "buildFinished" => BuildEvent::BuildFinished {
+            failure: string("error"),
+        },
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// Adds the fields of `other` to `value`
+fn merge(value: &mut Value, other: Value) {
+    if let (Value::Object(value), Value::Object(other)) = (value, other) {
+        value.extend(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_finished() {
+        let value = to_json(&BuildEvent::TaskFinished {
+            path: ":build".to_string(),
+            outcome: TaskOutcome::Failed {
+                message: "boom".to_string(),
+            },
+            duration: Duration::from_millis(1500),
+        });
+        assert_eq!(value["event"], "taskFinished");
+        assert_eq!(value["outcome"], "failed");
+        assert_eq!(value["message"], "boom");
+        assert_eq!(value["durationMs"], 1500);
+
+        let Some(BuildEvent::TaskFinished { path, duration, .. }) = from_json(&value) else {
+            panic!("expected a task finished event")
+        };
+        assert_eq!(path, ":build");
+        assert_eq!(duration, Duration::from_millis(1500));
+    }
+}
@@ -0,0 +1,63 @@
+//! Configures what the `spider` binary logs
+
+use crate::profile::Profile;
+use std::sync::OnceLock;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry, reload};
+
+/// How much is logged
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    /// Errors only
+    Quiet,
+    /// Warnings and errors
+    Warn,
+    /// The progress of the build, and warnings of every library
+    #[default]
+    Lifecycle,
+    /// Informational messages of every library
+    Info,
+    /// Debug messages of every library
+    Debug,
+}
+
+impl LogLevel {
+    /// Creates the filter for this level
+    fn filter(self) -> Targets {
+        let all = |level| Targets::new().with_default(level);
+        match self {
+            LogLevel::Quiet => all(LevelFilter::ERROR),
+            LogLevel::Warn => all(LevelFilter::WARN),
+            LogLevel::Lifecycle => all(LevelFilter::WARN).with_target("spider", LevelFilter::INFO),
+            LogLevel::Info => all(LevelFilter::INFO),
+            LogLevel::Debug => all(LevelFilter::DEBUG),
+        }
+    }
+}
+
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+/// Logs to stderr at the given level, recording the spans of the build into `profile` if given
+pub fn init(level: LogLevel, profile: Option<&Profile>) {
+    let (filter, handle) = reload::Layer::new(level.filter());
+    let fmt = tracing_subscriber::fmt::layer()
+        .without_time()
+        .with_target(false)
+        .with_level(false)
+        .with_writer(std::io::stderr)
+        .with_filter(filter);
+    tracing_subscriber::registry()
+        .with(fmt)
+        .with(profile.map(Profile::layer))
+        .init();
+    let _ = FILTER.set(handle);
+}
+
+/// Changes the level logged at, such as for each build run by the daemon
+pub fn set_level(level: LogLevel) {
+    if let Some(handle) = FILTER.get() {
+        let _ = handle.reload(level.filter());
+    }
+}
@@ -1,3 +1,225 @@
-fn main() {
-    println!("Hello, world!")
+//! # `spider`
+//! The spider command line front end
+
+mod cli;
+mod completions;
+mod console;
+mod continuous;
+#[cfg(unix)]
+mod daemon;
+mod events;
+mod logging;
+mod profile;
+
+use clap::{CommandFactory, Parser};
+use cli::Args;
+use completions::{COMPLETIONS_COMMAND, CompletionsArgs};
+use console::Console;
+use events::JsonEventWriter;
+use profile::Profile;
+use spider_core::error::{Error, Result, set_show_stacktrace};
+use spider_core::events::{BuildEvent, BuildListeners};
+use spider_core::invocation::spider::Spider;
+use spider_core::invocation::start_parameter::StartParameter;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|arg| arg == COMPLETIONS_COMMAND)
+    {
+        let completions = CompletionsArgs::parse_from(std::env::args_os().skip(1));
+        print!(
+            "{}",
+            completions::generate(completions.shell, &Args::command())
+        );
+        return ExitCode::SUCCESS;
+    }
+
+    let raw_args = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let args = Args::parse_args(&raw_args);
+    let profile = args.profile.as_ref().map(Profile::new);
+    logging::init(args.log_level(), profile.as_ref());
+    set_show_stacktrace(args.show_stacktrace());
+    if args.complete_tasks {
+        return complete_tasks(&args.start_parameter()).await;
+    }
+    #[cfg(unix)]
+    if args.daemon_server {
+        return serve_daemon().await;
+    }
+    if args.stop || args.status {
+        return report(daemon_command(&args).await);
+    }
+
+    let parameters = args.start_parameter();
+    let console = Console::new(args.console);
+    let listeners = match build_listeners(&args, &console) {
+        Ok(listeners) => listeners,
+        Err(e) => {
+            console.finish();
+            return report(Err(e.into()));
+        }
+    };
+    if args.continuous {
+        let result = continuous::run(&parameters, listeners).await;
+        console.finish();
+        return report(result.and(write_profile(profile.as_ref()).map_err(Into::into)));
+    }
+    let result = run(&args, raw_args, &parameters, listeners).await;
+    console.finish();
+    build_outcome(
+        result.and(write_profile(profile.as_ref()).map_err(|e| BuildFailure::Local(e.into()))),
+    )
+}
+
+/// Writes the profile of the build, if one was recorded
+fn write_profile(profile: Option<&Profile>) -> std::io::Result<()> {
+    profile.map_or(Ok(()), Profile::write)
+}
+
+/// Why a build failed
+#[derive(Debug)]
+enum BuildFailure {
+    /// The build failed in this process
+    Local(Error),
+    /// The build failed in the daemon, which rendered the report of the failure
+    #[cfg(unix)]
+    Daemon { report: String },
+}
+
+impl Display for BuildFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildFailure::Local(error) => write!(f, "{error}"),
+            #[cfg(unix)]
+            BuildFailure::Daemon { report } => write!(f, "{report}"),
+        }
+    }
+}
+
+/// Prints the outcome of a build
+fn build_outcome<E: Display>(result: std::result::Result<(), E>) -> ExitCode {
+    match result {
+        Ok(()) => {
+            eprintln!("BUILD SUCCESSFUL");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("BUILD FAILED");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Creates the listeners showing the progress of builds
+fn build_listeners(args: &Args, console: &Console) -> std::io::Result<BuildListeners> {
+    let mut listeners = BuildListeners::new();
+    if let Some(path) = &args.events {
+        let file = File::create(path)?;
+        listeners.add(JsonEventWriter::new(BufWriter::new(file)));
+    }
+    listeners.add(console.listener());
+    Ok(listeners)
+}
+
+/// Prints the tasks of the build for shell completion, printing nothing on failure
+async fn complete_tasks(parameters: &StartParameter) -> ExitCode {
+    let tasks = match Spider::with_start_parameter(parameters) {
+        Ok(mut spider) => completions::complete_tasks(&mut spider).await,
+        Err(e) => Err(e.into()),
+    };
+    match tasks {
+        Ok(tasks) => {
+            print!("{tasks}");
+            ExitCode::SUCCESS
+        }
+        Err(_) => ExitCode::FAILURE,
+    }
+}
+
+/// Runs the build, in the daemon if enabled
+async fn run(
+    args: &Args,
+    raw_args: Vec<String>,
+    parameters: &StartParameter,
+    listeners: BuildListeners,
+) -> std::result::Result<(), BuildFailure> {
+    #[cfg(unix)]
+    if daemon::enabled(args) {
+        return daemon::client::run_build(raw_args, listeners).await;
+    }
+    #[cfg(not(unix))]
+    drop((args, raw_args));
+
+    let result = match create_spider(parameters, listeners) {
+        Ok(mut spider) => spider.run(parameters).await,
+        Err(e) => Err(e.into()),
+    };
+    result.map_err(BuildFailure::Local)
+}
+
+/// Creates the spider of a build with the given listeners, telling them that the build finished
+/// if it can't be created
+fn create_spider(
+    parameters: &StartParameter,
+    listeners: BuildListeners,
+) -> std::io::Result<Spider> {
+    match Spider::with_start_parameter(parameters) {
+        Ok(mut spider) => {
+            spider.add_listener(listeners);
+            Ok(spider)
+        }
+        Err(e) => {
+            listeners.emit(BuildEvent::BuildFinished {
+                failure: Some(e.to_string()),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// Runs the daemon until it stops
+#[cfg(unix)]
+async fn serve_daemon() -> ExitCode {
+    let Some(socket) = daemon::socket_path() else {
+        return ExitCode::FAILURE;
+    };
+    match daemon::server::serve(&socket, daemon::idle_timeout()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!("daemon failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs `--stop` or `--status`
+async fn daemon_command(args: &Args) -> Result<()> {
+    #[cfg(unix)]
+    if args.stop {
+        daemon::client::stop().await
+    } else {
+        daemon::client::status().await
+    }
+    #[cfg(not(unix))]
+    Err(spider_core::error::ErrorKind::custom("the daemon is only supported on unix").into())
+}
+
+/// Prints the error of a command that isn't a build
+fn report(result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
 }
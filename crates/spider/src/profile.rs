@@ -0,0 +1,244 @@
+//! Writes a performance profile of a build in the Chrome Trace Event format.
+//!
+//! Every span emitted while the build runs is recorded each time it is entered and exited, on a
+//! track of the thread it ran on, so the profile can be opened with `chrome://tracing` or
+//! Perfetto.
+
+use serde_json::{Map, Value, json};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::Subscriber;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// A profile being recorded, written to its file once the build is finished
+#[derive(Debug, Clone)]
+pub struct Profile {
+    path: PathBuf,
+    recording: Arc<Recording>,
+}
+
+#[derive(Debug)]
+struct Recording {
+    start: Instant,
+    state: Mutex<RecordingState>,
+}
+
+#[derive(Debug, Default)]
+struct RecordingState {
+    events: Vec<Value>,
+    threads: HashSet<u64>,
+}
+
+impl Profile {
+    /// Creates a profile that will be written to `path`
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            recording: Arc::new(Recording {
+                start: Instant::now(),
+                state: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Creates the layer recording spans into this profile
+    pub fn layer(&self) -> ProfileLayer {
+        ProfileLayer {
+            recording: self.recording.clone(),
+        }
+    }
+
+    /// Writes the events recorded so far to the file of this profile
+    pub fn write(&self) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let state = self.recording.state.lock().unwrap();
+        let trace = json!({
+            "traceEvents": state.events,
+            "displayTimeUnit": "ms",
+        });
+        serde_json::to_writer(writer, &trace)?;
+        Ok(())
+    }
+}
+
+/// A [`Layer`] recording when spans are entered and exited
+#[derive(Debug)]
+pub struct ProfileLayer {
+    recording: Arc<Recording>,
+}
+
+/// The name shown for a span, its name followed by the values of its fields
+struct SpanLabel {
+    name: String,
+    args: Map<String, Value>,
+}
+
+impl ProfileLayer {
+    fn record(&self, phase: &str, label: &SpanLabel) {
+        let timestamp = self.recording.start.elapsed().as_secs_f64() * 1_000_000.0;
+        let tid = thread_id();
+        let pid = std::process::id();
+        let mut state = self.recording.state.lock().unwrap();
+        if state.threads.insert(tid) {
+            let thread = std::thread::current();
+            let name = thread.name().unwrap_or("thread");
+            state.events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": pid,
+                "tid": tid,
+                "args": { "name": format!("{name} #{tid}") },
+            }));
+        }
+        state.events.push(json!({
+            "name": label.name,
+            "ph": phase,
+            "ts": timestamp,
+            "pid": pid,
+            "tid": tid,
+            "args": label.args,
+        }));
+    }
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        let mut name = attrs.metadata().name().to_string();
+        for value in visitor.args.values() {
+            name.push(' ');
+            match value {
+                Value::String(value) => name.push_str(value),
+                value => name.push_str(&value.to_string()),
+            }
+        }
+        span.extensions_mut().insert(SpanLabel {
+            name,
+            args: visitor.args,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(label) = span.extensions().get::<SpanLabel>() {
+            self.record("B", label);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(label) = span.extensions().get::<SpanLabel>() {
+            self.record("E", label);
+        }
+    }
+}
+
+/// Collects the fields of a span as trace event arguments
+#[derive(Default)]
+struct FieldVisitor {
+    args: Map<String, Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.args.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.args.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.args.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.args
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// Gets the track of the current thread. Thread ids are assigned in the order threads first
+/// record an event.
+fn thread_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: Cell<Option<u64>> = const { Cell::new(None) };
+    }
+    ID.with(|id| match id.get() {
+        Some(tid) => tid,
+        None => {
+            let tid = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            id.set(Some(tid));
+            tid
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_spans_are_recorded() {
+        let profile = Profile::new("profile.json");
+        let subscriber = tracing_subscriber::registry().with(profile.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let _project = tracing::trace_span!("configure project", path = ":a").entered();
+            let _provider = tracing::trace_span!("resolve provider", key = "version").entered();
+        });
+
+        let mut output = vec![];
+        profile.write_to(&mut output).unwrap();
+        let trace: Value = serde_json::from_slice(&output).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        let phases = events
+            .iter()
+            .map(|event| {
+                (
+                    event["ph"].as_str().unwrap(),
+                    event["name"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases[1..],
+            [
+                ("B", "configure project :a"),
+                ("B", "resolve provider version"),
+                ("E", "resolve provider version"),
+                ("E", "configure project :a"),
+            ]
+        );
+        assert_eq!(phases[0].0, "M");
+        assert_eq!(events[1]["args"]["path"], ":a");
+    }
+}